@@ -1,58 +1,399 @@
+use crate::backend::{backend_from_name, Backend, BACKENDS};
 use crate::codegen::write_default_yaml_with_method;
+use crate::deserialize::{config_schema, Config, CONFIG_VERSION};
+use crate::doctor::doctor;
+use crate::error::{Error, Result};
+use crate::interpreter::{requested_interpreter, resolve};
+use crate::interrupt::install_handler;
+use crate::io::read_file_list;
+use crate::parser::{
+    config_in, convert_file, discover_config, migrate_file, parse, parse_with_overrides,
+    read_migrated, resolve_with_overrides, ConfigFormat, Override, CONFIG_NAMES,
+};
+use crate::report::REPORT_FORMATS;
+use crate::run::run;
 use crate::statistics::summarize;
-use crate::{audit::audit, python::py_handshake, utils::get_startup_options, watch::watch};
+use crate::sweep::sweep;
+use crate::utils::{StartupOptions, EXIT_SUCCESS, EXIT_TOTAL_FAILURE};
+use crate::{
+    audit::{audit, dry_run},
+    utils::get_startup_options,
+    watch::watch,
+};
 use clap::{
     crate_authors, crate_description, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
 };
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-pub fn launch() {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    let matches = start_app_and_get_matches();
+fn report_error(stdout: &mut StandardStream, message: &str) {
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
+    let _ = writeln!(stdout, "[ERRO] {}", message);
+    let _ = WriteColor::reset(stdout);
+}
 
-    if let Some(matches) = matches.subcommand_matches("audit") {
-        let startup_options = get_startup_options(matches, &mut stdout).unwrap();
-        audit(
-            &mut stdout,
-            &startup_options.filepath,
-            &startup_options.config_file,
-            &startup_options.result_file,
-            startup_options.verbosity,
-            startup_options.persist,
+/// The `--set` overrides given on the command line.
+fn overrides(matches: &ArgMatches<'_>) -> Result<Vec<Override>> {
+    matches
+        .values_of("set")
+        .into_iter()
+        .flatten()
+        .map(|o| o.parse::<Override>().map_err(Error::Config))
+        .collect()
+}
+
+/// Parse the config file with the `--profile` and the `--set` overrides applied, and show
+/// the values they set.
+fn load_config(
+    file: &str,
+    matches: &ArgMatches<'_>,
+    stdout: &mut StandardStream,
+) -> Result<Config> {
+    let (_, changes) = read_migrated(Path::new(file))?;
+    if !changes.is_empty() {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+        let _ = writeln!(
+            stdout,
+            "[WARN] {:?} is written in an older layout ({}), use `psc config migrate` to upgrade it.",
+            file,
+            changes.join(", ")
         );
+        let _ = WriteColor::reset(stdout);
     }
+    let overrides = overrides(matches)?;
+    let profile = matches.value_of("profile");
+    let (config, effective) = parse_with_overrides(file, profile, &overrides)?;
+    if let Some(profile) = profile {
+        let _ = writeln!(stdout, "[INFO] Using the {} profile.", profile);
+    }
+    for (key, value) in effective {
+        let _ = writeln!(
+            stdout,
+            "[INFO] {} = {} (set on the command line).",
+            key,
+            serde_json::to_string(&value).unwrap_or_default()
+        );
+    }
+    Ok(config)
+}
 
-    if let Some(matches) = matches.subcommand_matches("watch") {
-        if let Err(e) = writeln!(stdout, "[INFO] PySprint watch mode starting.") {
-            println!("Error writing to stdout: {}", e);
-        }
-        let startup_options = get_startup_options(matches, &mut stdout).unwrap();
-
-        py_handshake(&mut stdout);
+/// Find the Python interpreter, and set up the backend to run the evaluations with.
+fn get_backend(
+    startup_options: &StartupOptions,
+    config: &Config,
+    stdout: &mut StandardStream,
+) -> Result<Box<dyn Backend>> {
+    let requested = requested_interpreter(startup_options.python.as_deref(), config.python());
+    let explicit = requested.is_some();
 
-        if let Err(e) = writeln!(stdout, "[INFO] Watch started..") {
-            println!("Error writing to stdout: {}", e);
+    let interpreter = match resolve(requested) {
+        Ok(interpreter) => {
+            let _ = writeln!(stdout, "[INFO] Using {}.", interpreter);
+            Some(interpreter)
+        }
+        // the embedded interpreter is able to run without finding one on PATH
+        Err(e) if !explicit && startup_options.backend == "embedded" => {
+            let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+            let _ = writeln!(stdout, "[WARN] {} Using the embedded Python as is.", e);
+            let _ = WriteColor::reset(stdout);
+            None
         }
+        Err(e) => {
+            return Err(Error::Python(format!("Failed to find Python: {}", e)));
+        }
+    };
 
-        if let Err(e) = watch(
-            &mut stdout,
+    backend_from_name(
+        &startup_options.backend,
+        interpreter,
+        startup_options.show_python_output,
+    )
+}
+
+fn run_audit(matches: &ArgMatches<'_>, stdout: &mut StandardStream) -> Result<i32> {
+    let startup_options = get_startup_options(matches, stdout)?;
+    let config_file = &startup_options.config_file;
+    let config = load_config(config_file, matches, stdout)?;
+    let files = matches
+        .value_of("files-from")
+        .map(|list| read_file_list(list, &startup_options.filepath))
+        .transpose()?;
+    if matches.is_present("dry-run") {
+        dry_run(
+            stdout,
+            &startup_options.filepath,
+            &config,
+            files,
+            &startup_options.result_file,
+            startup_options.verbosity,
+            matches.is_present("show-code"),
+        )?;
+        return Ok(EXIT_SUCCESS);
+    }
+    if let Some(swept) = config.sweep.as_ref().filter(|swept| !swept.is_empty()) {
+        let summary = sweep(
+            stdout,
             &startup_options.filepath,
-            &startup_options.config_file,
+            config_file,
+            matches.value_of("profile"),
+            &overrides(matches)?,
+            swept,
+            files.as_deref(),
             &startup_options.result_file,
             startup_options.verbosity,
             startup_options.persist,
-        ) {
-            if let Err(e) = writeln!(stdout, "[ERROR] error watching..: {:?}", e) {
-                println!("Error writing to stdout: {}", e);
+            &startup_options.error_format,
+            |stdout, config| get_backend(&startup_options, config, stdout),
+        )?;
+        return Ok(summary.exit_code(startup_options.max_failures));
+    }
+    let backend = get_backend(&startup_options, &config, stdout)?;
+
+    let summary = audit(
+        stdout,
+        &startup_options.filepath,
+        config,
+        files,
+        &startup_options.result_file,
+        startup_options.verbosity,
+        startup_options.persist,
+        &startup_options.error_format,
+        backend,
+    )?;
+    Ok(summary.exit_code(startup_options.max_failures))
+}
+
+fn run_single(matches: &ArgMatches<'_>, stdout: &mut StandardStream) -> Result<i32> {
+    let file = Path::new(matches.value_of("file").unwrap_or_default());
+    if !file.is_file() {
+        return Err(Error::Config(format!("{:?} is not a file", file)));
+    }
+    let config_file = if let Some(config_file) = matches.value_of("config") {
+        PathBuf::from(config_file)
+    } else {
+        let dir = file
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        discover_config(dir).ok_or_else(|| {
+            Error::Config(format!(
+                "no {} found in {:?}, its parents or the user config directory, use `--config` to set one",
+                CONFIG_NAMES.join(", "),
+                dir
+            ))
+        })?
+    };
+    let _ = writeln!(stdout, "[INFO] Using config {:?}.", config_file);
+    let config = load_config(&config_file.to_string_lossy(), matches, stdout)?;
+
+    let result_file = matches
+        .value_of("result")
+        .or_else(|| matches.is_present("write").then_some("results.json"));
+    let startup_options = StartupOptions {
+        filepath: String::new(),
+        config_file: config_file.to_string_lossy().into_owned(),
+        result_file: result_file.unwrap_or_default().to_owned(),
+        verbosity: u8::try_from(matches.occurrences_of("verbosity")).unwrap_or(u8::MAX),
+        persist: matches.is_present("persist"),
+        dashboard: None,
+        backend: matches.value_of("backend").unwrap_or("embedded").into(),
+        python: matches.value_of("python").map(String::from),
+        show_python_output: matches.is_present("show-python-output"),
+        error_format: String::from("json"),
+        max_failures: None,
+    };
+    let backend = get_backend(&startup_options, &config, stdout)?;
+
+    let summary = run(
+        stdout,
+        file,
+        matches.value_of("sam").map(PathBuf::from),
+        matches.value_of("ref").map(PathBuf::from),
+        config,
+        result_file,
+        startup_options.verbosity,
+        startup_options.persist,
+        backend,
+    )?;
+    Ok(summary.exit_code(None))
+}
+
+fn run_watch(matches: &ArgMatches<'_>, stdout: &mut StandardStream) -> Result<i32> {
+    if let Err(e) = writeln!(stdout, "[INFO] PySprint watch mode starting.") {
+        println!("Error writing to stdout: {}", e);
+    }
+    let mut startup_options = get_startup_options(matches, stdout)?;
+    // without the dashboard, the Python output is all there is to see
+    startup_options.show_python_output = startup_options.dashboard.is_none();
+    let config = load_config(&startup_options.config_file, matches, stdout)?;
+    let backend = get_backend(&startup_options, &config, stdout)?;
+
+    let summary = watch(
+        stdout,
+        &startup_options.filepath,
+        config,
+        &startup_options.result_file,
+        startup_options.verbosity,
+        startup_options.persist,
+        startup_options.dashboard,
+        backend,
+    )?;
+    summary.report(stdout);
+    Ok(summary.exit_code(None))
+}
+
+fn run_config(matches: &ArgMatches<'_>, stdout: &mut StandardStream) -> Result<i32> {
+    if let Some(matches) = matches.subcommand_matches("show") {
+        // the config that applies here, which might be in a parent directory
+        let file = matches.value_of("file").map_or_else(
+            || discover_config(Path::new(".")).unwrap_or_else(|| PathBuf::from(CONFIG_NAMES[0])),
+            PathBuf::from,
+        );
+        let (resolved, _, _) = resolve_with_overrides(
+            &file.to_string_lossy(),
+            matches.value_of("profile"),
+            &overrides(matches)?,
+        )?;
+        let resolved = serde_yaml::to_string(&resolved)
+            .map_err(|e| Error::Config(format!("failed to serialize the config: {}", e)))?;
+        print!("{}", resolved);
+    }
+    if matches.subcommand_matches("schema").is_some() {
+        let schema = serde_json::to_string_pretty(&config_schema())
+            .map_err(|e| Error::Config(format!("failed to serialize the schema: {}", e)))?;
+        println!("{}", schema);
+    }
+    if let Some(matches) = matches.subcommand_matches("migrate") {
+        let file = &config_file_arg(matches);
+        if let Some((backup, changes)) = migrate_file(file)? {
+            let _ = writeln!(stdout, "[INFO] Backed up {:?} to {:?}.", file, backup);
+            for change in changes {
+                let _ = writeln!(stdout, "       {}", change);
             }
+            let _ = writeln!(
+                stdout,
+                "[INFO] {:?} is upgraded to version {}.",
+                file, CONFIG_VERSION
+            );
+            if has_comments(&backup) {
+                let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+                let _ = writeln!(
+                    stdout,
+                    "[WARN] The comments of {:?} are not kept, they're only in {:?}.",
+                    file, backup
+                );
+                let _ = WriteColor::reset(stdout);
+            }
+        } else {
+            let _ = writeln!(stdout, "[INFO] {:?} is up to date.", file);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        let file = config_file_arg(matches);
+        let format = matches
+            .value_of("to")
+            .unwrap_or_default()
+            .parse::<ConfigFormat>()
+            .map_err(Error::Config)?;
+        let output = matches
+            .value_of("output")
+            .map_or_else(|| file.with_extension(format.extension()), PathBuf::from);
+        if output.exists() {
+            return Err(Error::Config(format!(
+                "{:?} already exists, remove it or choose another `--output`",
+                output
+            )));
+        }
+        convert_file(&file, format, &output)?;
+        let _ = writeln!(stdout, "[INFO] Converted {:?} to {:?}.", file, output);
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+/// The config file given to a `config` subcommand, the one in the working directory by default.
+fn config_file_arg(matches: &ArgMatches<'_>) -> PathBuf {
+    matches.value_of("file").map_or_else(
+        || {
+            config_in(Path::new("."), &CONFIG_NAMES)
+                .unwrap_or_else(|| PathBuf::from(CONFIG_NAMES[0]))
+        },
+        PathBuf::from,
+    )
+}
+
+/// Whether `file` has comments, which a rewrite of it would lose.
+fn has_comments(file: &Path) -> bool {
+    std::fs::read_to_string(file).is_ok_and(|content| {
+        content
+            .lines()
+            .any(|line| line.trim_start().starts_with('#') || line.contains(" #"))
+    })
+}
+
+/// Print `error`, and return the exit code belonging to it.
+fn report_and_exit_code(stdout: &mut StandardStream, error: &Error) -> i32 {
+    report_error(stdout, &error.to_string());
+    error.exit_code()
+}
+
+/// Run the CLI, and return the process exit code.
+pub fn launch() -> i32 {
+    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+    let matches = start_app_and_get_matches();
+    let mut exit_code = EXIT_SUCCESS;
+
+    if matches.subcommand_matches("audit").is_some()
+        || matches.subcommand_matches("watch").is_some()
+        || matches.subcommand_matches("run").is_some()
+    {
+        if let Err(e) = install_handler() {
+            let _ = writeln!(stdout, "[WARN] Failed to install the Ctrl-C handler: {}", e);
         }
     }
 
+    if let Some(matches) = matches.subcommand_matches("audit") {
+        exit_code = run_audit(matches, &mut stdout)
+            .unwrap_or_else(|e| report_and_exit_code(&mut stdout, &e));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("run") {
+        exit_code = run_single(matches, &mut stdout)
+            .unwrap_or_else(|e| report_and_exit_code(&mut stdout, &e));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("watch") {
+        exit_code = run_watch(matches, &mut stdout)
+            .unwrap_or_else(|e| report_and_exit_code(&mut stdout, &e));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("doctor") {
+        let path = matches.value_of("path").unwrap_or(".");
+        let config = matches
+            .value_of("config")
+            .map(|config_file| Path::new(path).join(config_file))
+            .or_else(|| discover_config(Path::new(path)))
+            .and_then(|config_file| parse(&config_file.to_string_lossy()).ok());
+        exit_code = doctor(
+            &mut stdout,
+            matches.value_of("python"),
+            config.as_ref().and_then(Config::python),
+            matches.value_of("backend").unwrap_or("embedded"),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("config") {
+        exit_code = run_config(matches, &mut stdout)
+            .unwrap_or_else(|e| report_and_exit_code(&mut stdout, &e));
+    }
+
     if let Some(matches) = matches.subcommand_matches("summarize") {
         let result_file = matches.value_of("result").unwrap_or("results.json");
-        summarize(result_file);
+        exit_code = match summarize(result_file) {
+            Ok(true) => EXIT_SUCCESS,
+            Ok(false) => EXIT_TOTAL_FAILURE,
+            Err(e) => report_and_exit_code(&mut stdout, &e),
+        };
     }
 
     if let Some(matches) = matches.subcommand_matches("init") {
@@ -68,19 +409,69 @@ pub fn launch() {
             );
             let _ = WriteColor::reset(&mut stdout);
         }
-        let _ = write_default_yaml_with_method(
-            config_filepath.to_str().unwrap(),
-            matches.value_of("method").unwrap_or("fft"),
-        );
+        if let Err(e) =
+            write_default_yaml_with_method(config_path, matches.value_of("method").unwrap_or("fft"))
+        {
+            exit_code = report_and_exit_code(&mut stdout, &e.into());
+        }
     }
+    exit_code
+}
+
+const PYTHON_HELP: &str =
+    "the Python interpreter or environment root to use, overrides PSC_PYTHON and the config file";
+
+/// `--backend`, shared by the subcommands that start Python.
+fn backend_arg() -> Arg<'static, 'static> {
+    Arg::with_name("backend")
+        .long("backend")
+        .value_name("BACKEND")
+        .help("how to run the generated Python code")
+        .takes_value(true)
+        .possible_values(&BACKENDS)
+}
+
+/// `--profile`, applied before the `--set` overrides.
+fn profile_arg() -> Arg<'static, 'static> {
+    Arg::with_name("profile")
+        .long("profile")
+        .value_name("NAME")
+        .help("overlay the named profile of the config file on it")
+        .takes_value(true)
+}
+
+/// `--set`, it can be given several times.
+fn set_arg() -> Arg<'static, 'static> {
+    Arg::with_name("set")
+        .long("set")
+        .value_name("KEY=VALUE")
+        .help("override a value of the config file, e.g. method_details.windows=300")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .validator(|o| o.parse::<Override>().map(|_| ()))
 }
 
 fn start_app_and_get_matches() -> ArgMatches<'static> {
     App::new("PySprint-CLI")
         .setting(AppSettings::ColorAlways)
         .version(crate_version!())
-        .author(crate_authors!("\n"))
+        .author(crate_authors!())
         .about(crate_description!())
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("answer yes to every prompt")
+                .conflicts_with("no-input")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("no-input")
+                .long("no-input")
+                .help("never prompt, take the default answer instead (implied when stdin is not a terminal)")
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("watch")
                 .about("Watch a directory for changes, immediately rerun on events.")
@@ -99,9 +490,11 @@ fn start_app_and_get_matches() -> ArgMatches<'static> {
                         .short("c")
                         .long("config")
                         .value_name("CONFIG")
-                        .help("the config file to use")
+                        .help("the config file to use, the nearest eval.yaml, eval.toml or eval.json to PATH by default")
                         .takes_value(true),
                 )
+                .arg(profile_arg())
+                .arg(set_arg())
                 .arg(
                     Arg::with_name("result")
                         .short("r")
@@ -131,6 +524,34 @@ fn start_app_and_get_matches() -> ArgMatches<'static> {
                         .short("o")
                         .help("whether to override existing result file")
                         .takes_value(false),
+                )
+                .arg(backend_arg())
+                .arg(
+                    Arg::with_name("python")
+                        .long("python")
+                        .value_name("PYTHON")
+                        .help(PYTHON_HELP)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dashboard")
+                        .long("dashboard")
+                        .short("d")
+                        .help("show a live dashboard instead of the raw Python output")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("history")
+                        .long("history")
+                        .value_name("N")
+                        .help("the number of evaluated files to show on the dashboard")
+                        .takes_value(true)
+                        .requires("dashboard")
+                        .validator(|n| {
+                            n.parse::<usize>()
+                                .map(|_| ())
+                                .map_err(|_| format!("expected a number, found {}", n))
+                        }),
                 ),
         )
         .subcommand(
@@ -174,15 +595,159 @@ fn start_app_and_get_matches() -> ArgMatches<'static> {
                         .short("c")
                         .long("config")
                         .value_name("CONFIG")
-                        .help("the config file to use")
+                        .help("the config file to use, the nearest eval.yaml, eval.toml or eval.json to PATH by default")
                         .takes_value(true),
                 )
+                .arg(profile_arg())
+                .arg(set_arg())
                 .arg(
                     Arg::with_name("override")
                         .long("override")
                         .short("o")
                         .help("whether to override existing result file")
                         .takes_value(false),
+                )
+                .arg(backend_arg())
+                .arg(
+                    Arg::with_name("python")
+                        .long("python")
+                        .value_name("PYTHON")
+                        .help(PYTHON_HELP)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("show-python-output")
+                        .long("show-python-output")
+                        .help(
+                            "stream the Python output to the terminal, it's only logged otherwise",
+                        )
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("error-report")
+                        .long("error-report")
+                        .value_name("FORMAT")
+                        .help("the format of the error report, defaults to json")
+                        .takes_value(true)
+                        .possible_values(&REPORT_FORMATS),
+                )
+                .arg(
+                    Arg::with_name("fail-on-error")
+                        .long("fail-on-error")
+                        .help("exit with a non-zero code if any of the files failed")
+                        .takes_value(false)
+                        .conflicts_with("max-failures"),
+                )
+                .arg(
+                    Arg::with_name("max-failures")
+                        .long("max-failures")
+                        .value_name("N")
+                        .help("exit with a non-zero code if more than N files failed")
+                        .takes_value(true)
+                        .validator(|n| {
+                            n.parse::<usize>()
+                                .map(|_| ())
+                                .map_err(|_| format!("expected a number, found {}", n))
+                        }),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("show what would be evaluated, without starting Python or touching the result file"),
+                )
+                .arg(
+                    Arg::with_name("show-code")
+                        .long("show-code")
+                        .help("show the Python code generated for the first file in a dry run")
+                        .requires("dry-run"),
+                )
+                .arg(
+                    Arg::with_name("files-from")
+                        .long("files-from")
+                        .value_name("LIST")
+                        .help("only evaluate the files listed in LIST, one name per line")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Evaluate a single file, and print the coefficients.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("the interferogram to evaluate")
+                        .takes_value(true)
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("sam")
+                        .long("sam")
+                        .value_name("SAM")
+                        .help("the sample arm, in the same directory as FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ref")
+                        .long("ref")
+                        .value_name("REF")
+                        .help("the reference arm, in the same directory as FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("config")
+                        .short("c")
+                        .long("config")
+                        .value_name("CONFIG")
+                        .help("the config file to use, the nearest eval.yaml, eval.toml or eval.json by default")
+                        .takes_value(true),
+                )
+                .arg(profile_arg())
+                .arg(set_arg())
+                .arg(
+                    Arg::with_name("write")
+                        .short("w")
+                        .long("write")
+                        .help("write the result into the result file next to FILE")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("result")
+                        .short("r")
+                        .long("result")
+                        .value_name("RESULT")
+                        .help("the result file to write, implies --write")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("verbosity")
+                        .short("v")
+                        .help("increase the verbosity level of results")
+                        .multiple(true)
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("persist")
+                        .long("persist")
+                        .short("p")
+                        .help("persist the evaluation file")
+                        .takes_value(false),
+                )
+                .arg(backend_arg())
+                .arg(
+                    Arg::with_name("python")
+                        .long("python")
+                        .value_name("PYTHON")
+                        .help(PYTHON_HELP)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("show-python-output")
+                        .long("show-python-output")
+                        .help(
+                            "stream the Python output to the terminal, it's only logged otherwise",
+                        )
+                        .takes_value(false),
                 ),
         )
         .subcommand(
@@ -197,6 +762,33 @@ fn start_app_and_get_matches() -> ArgMatches<'static> {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Check whether the Python environment is able to run evaluations.")
+                .arg(
+                    Arg::with_name("path")
+                        .value_name("PATH")
+                        .help("the directory to look for the config file in")
+                        .takes_value(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("config")
+                        .short("c")
+                        .long("config")
+                        .value_name("CONFIG")
+                        .help("the config file to use, the nearest eval.yaml, eval.toml or eval.json to PATH by default")
+                        .takes_value(true),
+                )
+                .arg(backend_arg())
+                .arg(
+                    Arg::with_name("python")
+                        .long("python")
+                        .value_name("PYTHON")
+                        .help(PYTHON_HELP)
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("init")
                 .about("Write default configuration file")
@@ -218,6 +810,64 @@ fn start_app_and_get_matches() -> ArgMatches<'static> {
                         .possible_values(&["fft", "wft", "spp", "cff", "mm"]),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Inspect the configuration file.")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Print the fully resolved config, with the files it extends merged in.")
+                        .arg(
+                            Arg::with_name("file")
+                                .value_name("FILE")
+                                .help("the config file to show, the nearest eval.yaml, eval.toml or eval.json by default")
+                                .index(1),
+                        )
+                        .arg(profile_arg())
+                        .arg(set_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("schema")
+                        .about("Print the JSON Schema of the config file, for editors to complete and validate it with."),
+                )
+                .subcommand(
+                    SubCommand::with_name("migrate")
+                        .about("Upgrade the config file to the current layout, keeping a backup of it.")
+                        .arg(
+                            Arg::with_name("file")
+                                .value_name("FILE")
+                                .help("the config file to upgrade, eval.yaml, eval.toml or eval.json by default")
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("convert")
+                        .about("Write the config file in another format, the comments are not kept.")
+                        .arg(
+                            Arg::with_name("file")
+                                .value_name("FILE")
+                                .help("the config file to convert, eval.yaml, eval.toml or eval.json by default")
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("to")
+                                .long("to")
+                                .value_name("FORMAT")
+                                .help("the format to write")
+                                .takes_value(true)
+                                .required(true)
+                                .possible_values(&["yaml", "toml", "json"]),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .value_name("OUTPUT")
+                                .help("the file to write, FILE with the extension of the format by default")
+                                .takes_value(true),
+                        ),
+                ),
+        )
         .get_matches()
 }
 
@@ -1,249 +1,285 @@
-use crate::codegen::{render_generic_template, render_spp_template, write_tempfile_with_imports};
-use crate::deserialize::{MethodType, _Mod};
-use crate::io::get_files;
-use crate::parser::parse;
-use crate::python::{exec_py, py_handshake, write_err};
-use crate::utils::{get_process_bar_with_length, get_spinner, sort_by_arms};
-use itertools::izip;
+use crate::backend::Backend;
+use crate::codegen::{render_generic_template, render_spp_template, Templates};
+use crate::deserialize::{Config, MethodType};
+use crate::error::Result;
+use crate::evaluator::{group_into_jobs, invalid_mod, spp_arms, Evaluation, Evaluator, Event};
+use crate::io::{select_files, FileSelection};
+use crate::report::ErrorReport;
+use crate::sweep::combinations;
+use crate::utils::{get_process_bar_with_length, get_spinner, RunSummary};
+use indicatif::ProgressBar;
+use std::ffi::OsStr;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 
+/// Evaluate the files in `filepath`, showing the progress in the terminal, and write
+/// the report of the failed ones. Only `files` are evaluated if they're given,
+/// otherwise the ones selected by the config.
+#[allow(clippy::too_many_arguments)]
 pub fn audit(
     stdout: &mut StandardStream,
     filepath: &str,
-    config_file: &str,
+    config: Config,
+    files: Option<Vec<PathBuf>>,
     result_file: &str,
     verbosity: u8,
     persist: bool,
-) {
-    let mut counter = 0;
-    let mut traceback = String::new();
-    let config = parse(&format!("{}/{}", filepath, config_file)).unwrap();
+    error_format: &str,
+    backend: Box<dyn Backend>,
+) -> Result<RunSummary> {
+    let evaluation = evaluate(
+        stdout,
+        filepath,
+        config,
+        files,
+        result_file,
+        verbosity,
+        persist,
+        backend,
+    )?;
+    write_error_report(&evaluation.error_report(), filepath, error_format);
+    Ok(evaluation.summary)
+}
 
-    py_handshake(stdout);
-    let files = get_files(filepath, &config.load_options).unwrap();
-    if let Ok(debug_flag) = std::env::var("PYSPRINT_DEBUG") {
-        if debug_flag == "1" {
-            println!("FILES: {:#?}", &files);
-        }
+/// Write the report of the failed evaluations to `filepath`, if there's any.
+pub(crate) fn write_error_report(errors: &ErrorReport, filepath: &str, error_format: &str) {
+    if errors.is_empty() {
+        return;
+    }
+    let pb = get_spinner();
+    pb.set_message("Generating report..");
+    match errors.write(filepath, error_format) {
+        Ok(path) => pb.finish_with_message(&format!("Report generated at {:?}.", path)),
+        Err(e) => pb.finish_with_message(&format!("Failed to write the report: {}", e)),
+    }
+}
+
+/// Evaluate the files like `audit`, without writing the error report.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn evaluate(
+    stdout: &mut StandardStream,
+    filepath: &str,
+    config: Config,
+    files: Option<Vec<PathBuf>>,
+    result_file: &str,
+    verbosity: u8,
+    persist: bool,
+    backend: Box<dyn Backend>,
+) -> Result<Evaluation> {
+    // the SPP method evaluates every file at once, there's no progress to show
+    let is_spp = config.method.kind() == MethodType::SPPMethod;
+    let debug = matches!(std::env::var("PYSPRINT_DEBUG"), Ok(flag) if flag == "1");
+    let mut bar: Option<ProgressBar> = None;
+
+    let mut evaluator = Evaluator::new(config);
+    if let Some(files) = files {
+        evaluator = evaluator.files(files);
     }
-    let warn = config.method == MethodType::SPPMethod
-        || config.method == MethodType::CosFitMethod
-        || config.method == MethodType::MinMaxMethod;
-    let (mut ifgs, mut sams, mut refs) = sort_by_arms(&files, stdout, warn);
-    let modulo = config.load_options._mod;
-    match &config.method {
-        MethodType::SPPMethod => {
-            match modulo.unwrap() {
-                _Mod(3) => {}
-                _Mod(1) => {
-                    ifgs = files;
-                    sams = vec![];
-                    refs = vec![];
+    let evaluation = evaluator
+        .workdir(filepath)
+        .result_file(result_file)
+        .verbosity(verbosity)
+        .persist(persist)
+        .backend(backend)
+        .on_event(|event| match event {
+            Event::Warning(warning) => {
+                let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+                let _ = writeln!(stdout, "[WARN] {}", warning);
+                let _ = WriteColor::reset(stdout);
+            }
+            Event::Started { files, jobs } => {
+                if debug {
+                    println!("FILES: {:#?}", &files);
                 }
-                _Mod(-1) => {
-                    sams = vec![];
-                    refs = vec![];
+                if !is_spp {
+                    bar = Some(get_process_bar_with_length(jobs as u64));
                 }
-                _ => {
-                    panic!("mod field should be 3, 1 or -1, found {:?}", modulo);
+            }
+            Event::Evaluating { .. } => {
+                if let Some(bar) = &bar {
+                    bar.inc(1);
                 }
-            };
-
-            let code = render_spp_template(
-                &ifgs,
-                &refs,
-                &sams,
-                filepath,
-                &config,
-                result_file,
-                verbosity,
-                true,
-            );
-
-            if persist {
-                let _ = write_tempfile_with_imports("spp_eval", code.as_ref().unwrap(), filepath);
             }
-
-            if let Err(e) = exec_py(&code.unwrap(), stdout, false) {
-                let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
-                let py_error = format!("[ERRO] Python error:\n{:?}", e);
-                if let Err(e) = writeln!(stdout, "{}", py_error) {
-                    println!("Error writing to stdout: {}", e);
+            Event::Evaluated(result) => {
+                if let (true, Some(error)) = (is_spp, result.error) {
+                    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
+                    let _ = writeln!(stdout, "[ERRO] Python error:\n{}", error.traceback);
+                    let _ = WriteColor::reset(stdout);
                 }
-                let _ = WriteColor::reset(stdout);
             }
+            Event::Queued(_) | Event::Finished(_) => {}
+        })
+        .run()?;
+
+    let summary = evaluation.summary.clone();
+    if let Some(bar) = bar {
+        if summary.interrupted {
+            bar.abandon_with_message("Interrupted.");
+        } else {
+            bar.finish_with_message("Done.");
         }
-        _ => {
-            // TODO: this is a really ugly, almost copy-paste match statement
-            // there must be a way to solve this elegantly..
-            match modulo.unwrap() {
-                _Mod(3) => {
-                    let bar = get_process_bar_with_length(ifgs.len() as u64);
+    }
+    summary.report(stdout);
+    Ok(evaluation)
+}
 
-                    for (file, sam_, ref_) in izip!(&ifgs, &sams, &refs) {
-                        bar.inc(1);
-                        let code = render_generic_template(
-                            file.as_path().file_name().unwrap().to_str().unwrap(),
-                            filepath,
-                            &config,
-                            result_file,
-                            verbosity,
-                            true,
-                            Some(sam_),
-                            Some(ref_),
-                        );
-                        if persist {
-                            let _ = write_tempfile_with_imports(
-                                file.as_path().file_stem().unwrap().to_str().unwrap(),
-                                code.as_ref().unwrap(),
-                                filepath,
-                            );
-                        }
-                        // execute it
-                        if let Ok((e, tb)) = exec_py(&code.unwrap(), stdout, true) {
-                            if e {
-                                counter += 1;
-                                traceback.push_str(&format!(
-                                    "file: {}\terror: {}\n",
-                                    file.as_path()
-                                        .file_name()
-                                        .unwrap()
-                                        .to_str()
-                                        .unwrap_or("unknown filename"),
-                                    &tb
-                                ));
-                            }
-                        }
-                    }
-                    bar.finish_with_message("Done.");
-                    if counter > 0 {
-                        if let Err(e) =
-                            writeln!(stdout, "[INFO] {:?} files skipped or errored out.", counter)
-                        {
-                            println!("Error writing to stdout: {:?}", e);
-                        }
-                        let pb = get_spinner();
-                        pb.set_message("Generating report..");
-                        let _ = write_err(filepath, &traceback);
-                        pb.finish_with_message(&format!(
-                            "Report generated at `{}/errors.log`.",
-                            filepath
-                        ));
-                    }
-                }
-                _Mod(1) => {
-                    let bar = get_process_bar_with_length(files.len() as u64);
+/// Show what `audit` would evaluate in `filepath`, without starting Python or touching
+/// the result file. The code generated for the first file is shown if `show_code` is set.
+pub fn dry_run(
+    stdout: &mut StandardStream,
+    filepath: &str,
+    config: &Config,
+    files: Option<Vec<PathBuf>>,
+    result_file: &str,
+    verbosity: u8,
+    show_code: bool,
+) -> Result<()> {
+    let name = |path: &Path| {
+        path.file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or("unknown filename")
+            .to_owned()
+    };
+    let names = |paths: &[PathBuf]| paths.iter().map(|p| name(p)).collect::<Vec<_>>().join(", ");
 
-                    for file in &files {
-                        bar.inc(1);
-                        let code = render_generic_template(
-                            file.as_path().file_name().unwrap().to_str().unwrap(),
-                            filepath,
-                            &config,
-                            result_file,
-                            verbosity,
-                            true,
-                            None,
-                            None,
-                        );
-                        if persist {
-                            let _ = write_tempfile_with_imports(
-                                file.as_path().file_stem().unwrap().to_str().unwrap(),
-                                code.as_ref().unwrap(),
-                                filepath,
-                            );
-                        }
-                        // execute it
-                        if let Ok((e, tb)) = exec_py(&code.unwrap(), stdout, true) {
-                            if e {
-                                counter += 1;
-                                traceback.push_str(&format!(
-                                    "file: {}\terror: {}\n",
-                                    file.as_path()
-                                        .file_name()
-                                        .unwrap()
-                                        .to_str()
-                                        .unwrap_or("unknown filename"),
-                                    &tb
-                                ));
-                            }
-                        }
-                    }
-                    bar.finish_with_message("Done.");
-                    if counter > 0 {
-                        if let Err(e) =
-                            writeln!(stdout, "[INFO] {:?} files skipped or errored out.", counter)
-                        {
-                            println!("Error writing to stdout: {:?}", e);
-                        }
-                        let pb = get_spinner();
-                        pb.set_message("Generating report..");
-                        let _ = write_err(filepath, &traceback);
-                        pb.finish_with_message(&format!(
-                            "Report generated at `{}/errors.log`.",
-                            filepath
-                        ));
-                    }
-                }
-                _Mod(-1) => {
-                    let bar = get_process_bar_with_length(ifgs.len() as u64);
+    let selection = match files {
+        Some(selected) => FileSelection {
+            selected,
+            excluded: vec![],
+        },
+        None => select_files(filepath, &config.load_options)?,
+    };
+    let modulo = config.load_options._mod.unwrap_or_default();
+
+    let _ = writeln!(
+        stdout,
+        "[INFO] Dry run, nothing is evaluated and the result file is left untouched."
+    );
+    let _ = writeln!(stdout, "Method: {}", config.method.name());
+    let _ = writeln!(stdout, "  mod: {}", modulo.0);
+    for (key, value) in config.parameters() {
+        let _ = writeln!(stdout, "  {}: {}", key, value);
+    }
+    if let Some(sweep) = config.sweep.as_ref().filter(|sweep| !sweep.is_empty()) {
+        let _ = writeln!(
+            stdout,
+            "Sweep ({} combinations, each evaluating the files below):",
+            combinations(sweep)?.len()
+        );
+        for (key, values) in sweep {
+            let values = values
+                .values()
+                .iter()
+                .map(|value| serde_json::to_string(value).unwrap_or_default())
+                .collect::<Vec<_>>();
+            let _ = writeln!(stdout, "  {}: {}", key, values.join(", "));
+        }
+    }
 
-                    for file in &ifgs {
-                        bar.inc(1);
-                        let code = render_generic_template(
-                            file.as_path().file_name().unwrap().to_str().unwrap(),
-                            filepath,
-                            &config,
-                            result_file,
-                            verbosity,
-                            true,
-                            None,
-                            None,
-                        );
-                        if persist {
-                            let _ = write_tempfile_with_imports(
-                                file.as_path().file_stem().unwrap().to_str().unwrap(),
-                                code.as_ref().unwrap(),
-                                filepath,
-                            );
-                        }
-                        // execute it
-                        if let Ok((e, tb)) = exec_py(&code.unwrap(), stdout, true) {
-                            if e {
-                                counter += 1;
-                                traceback.push_str(&format!(
-                                    "file: {}\terror: {}\n",
-                                    file.as_path()
-                                        .file_name()
-                                        .unwrap()
-                                        .to_str()
-                                        .unwrap_or("unknown filename"),
-                                    &tb
-                                ));
-                            }
-                        }
-                    }
-                    bar.finish_with_message("Done.");
-                    if counter > 0 {
-                        if let Err(e) =
-                            writeln!(stdout, "[INFO] {:?} files skipped or errored out.", counter)
-                        {
-                            println!("Error writing to stdout: {:?}", e);
-                        }
-                        let pb = get_spinner();
-                        pb.set_message("Generating report..");
-                        let _ = write_err(filepath, &traceback);
-                        pb.finish_with_message(&format!(
-                            "Report generated at `{}/errors.log`.",
-                            filepath
-                        ));
-                    }
+    let _ = writeln!(stdout, "Selected files ({}):", selection.selected.len());
+    for file in &selection.selected {
+        let _ = writeln!(stdout, "  {}", name(file));
+    }
+    if !selection.excluded.is_empty() {
+        let _ = writeln!(stdout, "Excluded files ({}):", selection.excluded.len());
+        for (file, reason) in &selection.excluded {
+            let _ = writeln!(stdout, "  {}: {}", name(file), reason);
+        }
+    }
+
+    // the files that are evaluated, and the code of the first evaluation if it's asked for
+    // Python is not started, so the code is shown with the newest templates
+    let templates = Templates::default();
+    let (grouped, first_job) = if config.method.kind() == MethodType::SPPMethod {
+        let (ifgs, sams, refs) =
+            spp_arms(&selection.selected, modulo).ok_or_else(|| invalid_mod(modulo))?;
+        let _ = writeln!(stdout, "Evaluations (1, every file at once):");
+        let _ = writeln!(stdout, "  ifg: {}", names(&ifgs));
+        if !sams.is_empty() {
+            let _ = writeln!(stdout, "  sam: {}", names(&sams));
+            let _ = writeln!(stdout, "  ref: {}", names(&refs));
+        }
+        let code = show_code
+            .then(|| {
+                render_spp_template(
+                    &templates,
+                    &ifgs,
+                    &refs,
+                    &sams,
+                    filepath,
+                    config,
+                    result_file,
+                    verbosity,
+                    true,
+                )
+            })
+            .transpose()?
+            .map(|code| (String::from("spp_eval"), code));
+        (ifgs.into_iter().chain(sams).chain(refs).collect(), code)
+    } else {
+        let jobs =
+            group_into_jobs(&selection.selected, modulo).ok_or_else(|| invalid_mod(modulo))?;
+        let _ = writeln!(stdout, "Evaluations ({}):", jobs.len());
+        for (i, (file, sam_, ref_)) in jobs.iter().enumerate() {
+            match (sam_, ref_) {
+                (Some(sam_), Some(ref_)) => {
+                    let _ = writeln!(
+                        stdout,
+                        "  {}. {} (sam: {}, ref: {})",
+                        i + 1,
+                        name(file),
+                        name(sam_),
+                        name(ref_)
+                    );
                 }
                 _ => {
-                    panic!("mod field should be 3, 1 or -1, found {:?}", modulo);
+                    let _ = writeln!(stdout, "  {}. {}", i + 1, name(file));
                 }
-            };
+            }
         }
+        let code = match jobs.first() {
+            Some((file, sam_, ref_)) if show_code => {
+                let filename = name(file);
+                let code = render_generic_template(
+                    &templates,
+                    &filename,
+                    filepath,
+                    config,
+                    result_file,
+                    verbosity,
+                    true,
+                    sam_.as_ref(),
+                    ref_.as_ref(),
+                )?;
+                Some((filename, code))
+            }
+            _ => None,
+        };
+        let grouped = jobs
+            .into_iter()
+            .flat_map(|(file, sam_, ref_)| std::iter::once(file).chain(sam_).chain(ref_))
+            .collect::<Vec<_>>();
+        (grouped, code)
+    };
+
+    let left_out = selection
+        .selected
+        .iter()
+        .filter(|file| !grouped.contains(file))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !left_out.is_empty() {
+        let _ = writeln!(
+            stdout,
+            "Not evaluated due to the arm grouping: {}",
+            names(&left_out)
+        );
+    }
+
+    if let Some((filename, code)) = first_job {
+        let _ = writeln!(stdout, "Generated code for {}:", filename);
+        let _ = writeln!(stdout, "{}", code.trim_end());
     }
+    Ok(())
 }
@@ -0,0 +1,326 @@
+use crate::codegen::{Templates, IMPORT_HEADERS};
+use crate::error::{Error, Result};
+use crate::interpreter::PythonInterpreter;
+use crate::interrupt::{cancelled, Watchdog};
+use crate::python::{check_pysprint_version, exec_py, py_handshake, DESCRIBE};
+use crate::utils::get_spinner;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tempfile::Builder;
+
+/// The details of the Python exception an evaluation failed with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ExceptionInfo {
+    pub exception_type: String,
+    pub message: String,
+    /// The full traceback, starting from the generated code.
+    pub traceback: String,
+    /// The line of the generated code the exception was raised from, if known.
+    pub line: Option<usize>,
+}
+
+/// The outcome of executing a piece of generated Python code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Execution {
+    /// Whether the evaluation resulted in an error.
+    pub is_err: bool,
+    /// The error, empty on success.
+    pub traceback: String,
+    /// Whether the evaluation was aborted because it exceeded its time limit.
+    pub timed_out: bool,
+    /// Everything the code printed to stdout and stderr, including the error.
+    pub output: String,
+    /// The exception the evaluation failed with, if it's known.
+    pub exception: Option<ExceptionInfo>,
+}
+
+/// What the handshake found out about the Python environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Handshake {
+    /// Not fatal, but worth showing.
+    pub warnings: Vec<String>,
+    /// The templates that work with the installed pysprint.
+    pub templates: Templates,
+}
+
+/// Something that's able to run the generated Python code.
+pub trait Backend {
+    /// Check whether the backend is able to run evaluations, and fail early if it isn't.
+    fn handshake(&mut self) -> Result<Handshake>;
+
+    /// Run `code`, and abort it after `timeout`. Errors are returned, not printed.
+    /// What the code prints is captured, and only shown if the backend was asked to.
+    fn execute(&mut self, code: &str, timeout: Option<Duration>) -> Result<Execution>;
+}
+
+pub const BACKENDS: [&str; 2] = ["embedded", "subprocess"];
+
+/// Construct a backend by its name, as given on the command line.
+/// The Python output is streamed to the terminal if `show_output` is set.
+pub fn backend_from_name(
+    name: &str,
+    interpreter: Option<PythonInterpreter>,
+    show_output: bool,
+) -> Result<Box<dyn Backend>> {
+    match name {
+        "embedded" => Ok(Box::new(EmbeddedBackend::new(interpreter, show_output))),
+        "subprocess" => Ok(Box::new(SubprocessBackend::new(
+            interpreter
+                .ok_or_else(|| Error::Python(String::from("no Python interpreter found")))?
+                .executable,
+            show_output,
+        ))),
+        other => Err(Error::Config(format!(
+            "expected valid backend name, found {}",
+            other
+        ))),
+    }
+}
+
+/// Runs the code in the Python interpreter embedded into this process.
+#[derive(Debug, Default, Clone)]
+pub struct EmbeddedBackend {
+    /// The installation to take the standard library and packages from.
+    interpreter: Option<PythonInterpreter>,
+    show_output: bool,
+}
+
+impl EmbeddedBackend {
+    pub const fn new(interpreter: Option<PythonInterpreter>, show_output: bool) -> Self {
+        Self {
+            interpreter,
+            show_output,
+        }
+    }
+}
+
+impl Backend for EmbeddedBackend {
+    fn handshake(&mut self) -> Result<Handshake> {
+        py_handshake(self.interpreter.as_ref())
+    }
+
+    fn execute(&mut self, code: &str, timeout: Option<Duration>) -> Result<Execution> {
+        let watchdog = timeout.map(Watchdog::start);
+        let outcome = exec_py(code, self.show_output);
+        let timed_out = watchdog.is_some_and(Watchdog::stop);
+
+        let mut execution = outcome?;
+        // the subprocess gets the error printed by Python, do the same here
+        if execution.is_err {
+            execution.output.push_str(&execution.traceback);
+            execution.output.push('\n');
+        }
+        execution.timed_out = execution.is_err && timed_out;
+        Ok(execution)
+    }
+}
+
+/// Runs the code with a separate Python process, so a crashing
+/// C extension can't take us down.
+#[derive(Debug, Clone)]
+pub struct SubprocessBackend {
+    interpreter: PathBuf,
+    show_output: bool,
+}
+
+impl SubprocessBackend {
+    pub const fn new(interpreter: PathBuf, show_output: bool) -> Self {
+        Self {
+            interpreter,
+            show_output,
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.interpreter);
+
+        // Put the child into its own process group, so Ctrl-C only reaches us,
+        // and we get to decide whether to let it finish.
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                command.process_group(0);
+            } else if #[cfg(windows)] {
+                // CREATE_NEW_PROCESS_GROUP
+                command.creation_flags(0x0000_0200);
+            }
+        }
+        command
+    }
+}
+
+/// Read everything from `reader` in the background, so a chatty child can't fill up
+/// the pipe and block. Lines are also appended to the shared `log`, and passed
+/// through to `echo` if there's one.
+fn read_in_background<R, W>(
+    reader: Option<R>,
+    log: Arc<Mutex<String>>,
+    mut echo: Option<W>,
+) -> JoinHandle<String>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut buffer = String::new();
+        let Some(reader) = reader else {
+            return buffer;
+        };
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        while matches!(reader.read_line(&mut line), Ok(n) if n > 0) {
+            if let Some(echo) = echo.as_mut() {
+                let _ = echo.write_all(line.as_bytes());
+                let _ = echo.flush();
+            }
+            if let Ok(mut log) = log.lock() {
+                log.push_str(&line);
+            }
+            buffer.push_str(&line);
+            line.clear();
+        }
+        buffer
+    })
+}
+
+/// Runs the generated script given as the first argument, and writes the description
+/// of the exception it raised to the file given as the second.
+const DRIVER: &str = r#"
+import runpy, sys
+script, report = sys.argv[1], sys.argv[2]
+try:
+    runpy.run_path(script, run_name="__main__")
+except SystemExit as e:
+    if e.code not in (None, 0):
+        with open(report, "w") as f:
+            f.write(describe(type(e), e, e.__traceback__, script))
+    raise
+except BaseException as e:
+    description = describe(type(e), e, e.__traceback__, script)
+    with open(report, "w") as f:
+        f.write(description)
+    # print it without the frames of the driver and runpy
+    sys.stderr.write(json.loads(description)["traceback"])
+    sys.exit(1)
+"#;
+
+/// Wait for the child to exit, and kill it if it's cancelled or runs out of time.
+/// Returns the exit status (if it exited on its own) and whether it timed out.
+fn wait_or_kill(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<(Option<std::process::ExitStatus>, bool)> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((Some(status), false));
+        }
+        let timed_out = timeout.is_some_and(|t| started.elapsed() > t);
+        if timed_out || cancelled() {
+            child.kill()?;
+            child.wait()?;
+            return Ok((None, timed_out));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+impl Backend for SubprocessBackend {
+    fn handshake(&mut self) -> Result<Handshake> {
+        let pb = get_spinner();
+
+        pb.set_message(&format!("Checking {:?}..", self.interpreter));
+        let output = self
+            .command()
+            .args([
+                "-c",
+                "import numpy, pysprint, matplotlib.pyplot; print(pysprint.__version__)",
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .ok()
+            .filter(|o| o.status.success());
+        pb.finish_and_clear();
+        let Some(output) = output else {
+            return Err(Error::Python(format!(
+                "Python interpreter at {:?} failed.. Do you have pysprint installed?\nRun `psc doctor --backend subprocess` for details.",
+                self.interpreter
+            )));
+        };
+
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        check_pysprint_version(Some(version).filter(|v| !v.is_empty()))
+    }
+
+    fn execute(&mut self, code: &str, timeout: Option<Duration>) -> Result<Execution> {
+        // the embedded interpreter has these imported already
+        let mut script = Builder::new().suffix(".py").tempfile()?;
+        script.write_all(IMPORT_HEADERS.as_bytes())?;
+        script.write_all(code.as_bytes())?;
+        script.flush()?;
+        // the driver describes the exception here, if there's one
+        let report = Builder::new().suffix(".json").tempfile()?;
+
+        let mut child = self
+            .command()
+            .args(["-c", &format!("{}{}", DESCRIBE, DRIVER)])
+            .arg(script.path())
+            .arg(report.path())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let log = Arc::new(Mutex::new(String::new()));
+        let show = self.show_output;
+        let child_stdout = read_in_background(
+            child.stdout.take(),
+            Arc::clone(&log),
+            show.then(std::io::stdout),
+        );
+        let child_stderr = read_in_background(
+            child.stderr.take(),
+            Arc::clone(&log),
+            show.then(std::io::stderr),
+        );
+
+        let (status, timed_out) = wait_or_kill(&mut child, timeout)?;
+        let _ = child_stdout.join();
+        let errors = child_stderr.join().unwrap_or_default();
+        let output = log.lock().map(|log| log.clone()).unwrap_or_default();
+        let header_lines = IMPORT_HEADERS.lines().count();
+        let exception = std::fs::read_to_string(report.path())
+            .ok()
+            .and_then(|report| serde_json::from_str::<ExceptionInfo>(&report).ok())
+            .map(|mut exception| {
+                // point into the generated code, not the script with the imports
+                exception.line = exception
+                    .line
+                    .and_then(|line| line.checked_sub(header_lines));
+                exception
+            });
+
+        let is_err = !status.is_some_and(|s| s.success());
+        let traceback = if !is_err {
+            String::new()
+        } else if let Some(status) = status {
+            format!("{}\n{}", errors.trim_end(), status)
+        } else {
+            format!("{}\nterminated", errors.trim_end())
+        };
+
+        Ok(Execution {
+            is_err,
+            traceback,
+            timed_out,
+            output,
+            exception,
+        })
+    }
+}
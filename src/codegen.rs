@@ -1,12 +1,17 @@
-use crate::deserialize::Config;
+use crate::deserialize::{Config, CONFIG_VERSION};
+use crate::error::Error;
+use crate::utils::InputMode;
 use lazy_static::lazy_static;
+use std::ffi::OsStr;
+use std::fmt;
 use std::io;
 use std::io::Write;
 use std::path::PathBuf;
+use std::str::FromStr;
 use tempfile::Builder;
 use tera::Tera;
 
-const IMPORT_HEADERS: &str = r#"import numpy as np
+pub(crate) const IMPORT_HEADERS: &str = r#"import numpy as np
 import pysprint as ps
 import matplotlib.pyplot as plt
 
@@ -78,17 +83,13 @@ ifg._optimizer({{reference_frequency}}, {{ order }}, initial_region_ratio=0.05,
 {% elif methodname == "WFTMethod" %}
 ifg.cover(
     {% if windows %}{{ windows }}{% else %}300{% endif %},
-    {% if fwhm and not std %}fwhm={{ fwhm }},{% endif %}
-    {% if std and not fwhm %}std={{ std }},{% endif %}
-    {%if not std and not fwhm %}fwhm=0.05{% endif %}
+    {% if std %}std={{ std }},{% elif fwhm %}fwhm={{ fwhm }},{% else %}fwhm=0.05{% endif %}
 )
 
 ifg.{%- if is_audit -%}_{%- endif -%}calculate({{ reference_frequency }}, {{ order }}, silent={%- if is_audit -%}True{%- else -%}False{%- endif -%}, parallel={% if parallel %}True{% else %}False{% endif %}, fastmath=False)
 {% elif methodname == "MinMaxMethod" %}
 ifg.init_edit_session(
-    {% if min and max %}
-    side="both"
-    {% elif min %}
+    {% if min %}
     side="min"
     {% elif max %}
     side="max"
@@ -98,11 +99,7 @@ ifg.init_edit_session(
 )
 plt.show(block=True)
 ifg.calculate({{ reference_frequency }}, {{ order }}, scan=True,
-    {% if min and max %}
-    onesided=False
-    {% elif min %}
-    onesided=True
-    {% elif max %}
+    {% if min or max %}
     onesided=True
     {% else %}
     onesided=False
@@ -119,6 +116,7 @@ plt.show(block=True)
 # if you are working with the generated file, the part below can be safely commented out
 
 fragment = ps.utils._prepare_json_fragment(ifg, "{{ filename_raw }}", x_before_transform, y_before_transform, verbosity={{ verbosity }})
+{% if profile %}fragment["profile"] = {{ profile | json_encode() }}{% endif %}
 ps.utils._write_or_update_json_fragment("{{ workdir }}/{{ result_file }}", fragment, "{{ filename_raw }}")
 
 {%- for cmd in aet %}
@@ -162,7 +160,14 @@ for ifg in myspp:
     ifg.open_SPP_panel(header="comment")
 {% endif %}
 
-myspp.calculate({{ reference_frequency }}, {{ order }}, show_graph=False)
+dispersion, _, _ = myspp.calculate({{ reference_frequency }}, {{ order }}, show_graph=False)
+
+# if you are working with the generated file, the part below can be safely commented out
+
+fragment = {name: float(value) for name, value in zip(("GD", "GDD", "TOD", "FOD", "QOD", "SOD"), dispersion)}
+fragment["method"] = "spp"
+{% if profile %}fragment["profile"] = {{ profile | json_encode() }}{% endif %}
+ps.utils._write_or_update_json_fragment("{{ workdir }}/{{ result_file }}", fragment, "spp_eval")
 
 {% for cmd in aet %}
 {{ cmd -}}
@@ -173,6 +178,122 @@ myspp.calculate({{ reference_frequency }}, {{ order }}, show_graph=False)
     };
 }
 
+/// A pysprint version, only the numeric `major.minor.patch` part is considered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PysprintVersion(pub u32, pub u32, pub u32);
+
+impl FromStr for PysprintVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // drop suffixes like `.dev0` or `rc1`
+        let mut parts = s.trim().split('.').map(|part| {
+            part.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse::<u32>()
+        });
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), patch) => {
+                Ok(Self(major, minor, patch.and_then(Result::ok).unwrap_or(0)))
+            }
+            _ => Err(format!("expected valid pysprint version, found {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for PysprintVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// A template in `TEMPLATES`, and the range of pysprint versions it works with.
+/// The templates call private pysprint APIs, which are subject to change between releases.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateVariant {
+    pub name: &'static str,
+    pub min_version: PysprintVersion,
+    /// exclusive
+    pub max_version: PysprintVersion,
+}
+
+impl TemplateVariant {
+    pub fn supports(&self, version: PysprintVersion) -> bool {
+        self.min_version <= version && version < self.max_version
+    }
+}
+
+// The bounds are the pysprint releases the templates are known to work with. Before
+// letting a new release in, check that the private APIs the templates call are unchanged:
+// `ps.utils._prepare_json_fragment` and `ps.utils._write_or_update_json_fragment` for the
+// generic template, and `ps.SPPMethod.calculate` for the SPP one. Add a new variant
+// (newest first) instead of widening the range if they did change.
+
+/// The variants of the generic template, the newest ones first.
+pub const GENERIC_TEMPLATES: [TemplateVariant; 1] = [TemplateVariant {
+    name: "pstemplate.py_t",
+    min_version: PysprintVersion(0, 28, 0),
+    max_version: PysprintVersion(0, 31, 0),
+}];
+
+/// The variants of the SPP template, the newest ones first.
+pub const SPP_TEMPLATES: [TemplateVariant; 1] = [TemplateVariant {
+    name: "spp.py_t",
+    min_version: PysprintVersion(0, 28, 0),
+    max_version: PysprintVersion(0, 31, 0),
+}];
+
+/// The templates to render, selected for the installed pysprint version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Templates {
+    pub generic: &'static str,
+    pub spp: &'static str,
+}
+
+impl Default for Templates {
+    /// The newest variants, for when the pysprint version is not checked.
+    fn default() -> Self {
+        Self {
+            generic: GENERIC_TEMPLATES[0].name,
+            spp: SPP_TEMPLATES[0].name,
+        }
+    }
+}
+
+/// Pick the newest variant of a template that supports `version`.
+fn select_variant(
+    template: &str,
+    variants: &[TemplateVariant],
+    version: PysprintVersion,
+) -> Result<&'static str, Error> {
+    if let Some(variant) = variants.iter().find(|v| v.supports(version)) {
+        return Ok(variant.name);
+    }
+    let ranges = variants
+        .iter()
+        .map(|v| format!(">={},<{}", v.min_version, v.max_version))
+        .collect::<Vec<_>>();
+    Err(Error::Python(format!(
+        "pysprint {} is not supported, the {} template requires pysprint {}.
+       Install a supported version with `pip install \"pysprint{}\"`.",
+        version,
+        template,
+        ranges.join(" or "),
+        ranges.first().map_or("", String::as_str)
+    )))
+}
+
+/// Select the template variants compatible with the given pysprint version,
+/// and fail if there's none for some template.
+pub fn select_templates_for(version: &str) -> Result<Templates, Error> {
+    let version = version.parse::<PysprintVersion>().map_err(Error::Python)?;
+    Ok(Templates {
+        generic: select_variant("generic", &GENERIC_TEMPLATES, version)?,
+        spp: select_variant("SPP", &SPP_TEMPLATES, version)?,
+    })
+}
+
 pub fn write_tempfile_with_imports(name: &str, content: &str, path: &str) -> std::io::Result<()> {
     // we also write the import headers to the generated file
     let mut accumulator = IMPORT_HEADERS.to_owned();
@@ -188,6 +309,7 @@ pub fn write_tempfile_with_imports(name: &str, content: &str, path: &str) -> std
 
 #[allow(clippy::too_many_arguments)]
 pub fn render_spp_template(
+    templates: &Templates,
     ifg_files: &[PathBuf],
     ref_files: &[PathBuf],
     sam_files: &[PathBuf],
@@ -196,8 +318,8 @@ pub fn render_spp_template(
     result_file: &str,
     verbosity: u8,
     is_audit: bool,
-) -> Result<std::string::String, tera::Error> {
-    let mut context = config.insert_into_ctx().unwrap();
+) -> Result<std::string::String, Error> {
+    let mut context = config.insert_into_ctx()?;
 
     let ifgs = ifg_files
         .iter()
@@ -224,11 +346,12 @@ pub fn render_spp_template(
     context.insert("is_audit", &is_audit);
 
     // render as String
-    TEMPLATES.render("spp.py_t", &context)
+    Ok(TEMPLATES.render(templates.spp, &context)?)
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn render_generic_template(
+    templates: &Templates,
     file: &str,
     path: &str,
     config: &Config,
@@ -237,16 +360,16 @@ pub fn render_generic_template(
     is_audit: bool,
     sam_arm: Option<&PathBuf>,
     ref_arm: Option<&PathBuf>,
-) -> Result<std::string::String, tera::Error> {
-    let mut context = config.insert_into_ctx().unwrap();
+) -> Result<std::string::String, Error> {
+    let mut context = config.insert_into_ctx()?;
 
     if let Some(arm) = sam_arm {
-        let f2 = arm.as_path().file_name().unwrap().to_str().unwrap_or("");
+        let f2 = arm.file_name().and_then(OsStr::to_str).unwrap_or("");
         context.insert("filename2", &format!("{}/{}", path, f2));
     }
 
     if let Some(arm) = ref_arm {
-        let f3 = arm.as_path().file_name().unwrap().to_str().unwrap_or("");
+        let f3 = arm.file_name().and_then(OsStr::to_str).unwrap_or("");
         context.insert("filename3", &format!("{}/{}", path, f3));
     }
 
@@ -261,7 +384,7 @@ pub fn render_generic_template(
     context.insert("filename", &format!("{}/{}", path, file));
 
     // render as String
-    TEMPLATES.render("pstemplate.py_t", &context)
+    Ok(TEMPLATES.render(templates.generic, &context)?)
 }
 
 pub fn write_default_yaml_with_method(path: &str, method_str: &str) -> std::io::Result<()> {
@@ -275,7 +398,8 @@ pub fn write_default_yaml_with_method(path: &str, method_str: &str) -> std::io::
     std::fs::write(
         cfg_path,
         &format!(
-            r#"load_options:
+            r#"version: {}
+load_options:
   extensions:
     - "trt"
     - "txt"
@@ -297,19 +421,7 @@ preprocess:
 method:
   {}
 method_details:
-  heatmap: false
-  windows: 200
-  fwhm: 0.05 # PHz
-  # std: 0.05 # PHz
-  parallel: false
-  plot: false
-  only_phase: false
-  min: false
-  max: false
-  both: false
-  eager: false
-  detach: true
-# before_evaluate:
+{}# before_evaluate:
 # - print('before_evaluate')
 evaluate:
   reference_frequency: 2.355
@@ -318,33 +430,83 @@ evaluate:
 # after_evaluate:
 #   - print('and after evaluate too..')
 "#,
-            modulo, method_str
+            CONFIG_VERSION,
+            modulo,
+            method_str,
+            default_method_details(method_str)
         )
         .as_bytes(),
     )?;
     Ok(())
 }
 
+/// The `method_details` written into the default config, only the ones the method takes.
+fn default_method_details(method_str: &str) -> &'static str {
+    match method_str {
+        "wft" => {
+            r"  heatmap: false
+  windows: 200
+  fwhm: 0.05 # PHz
+  # std: 0.05 # PHz, instead of fwhm
+  parallel: false
+  plot: false
+"
+        }
+        "mm" => {
+            r"  plot: false
+  # both sides are used unless one of them is set
+  min: false
+  max: false
+"
+        }
+        "spp" => {
+            r"  plot: false
+  eager: false
+  detach: true
+"
+        }
+        _ => {
+            "  plot: false
+"
+        }
+    }
+}
+
 pub fn write_default_yaml(path: &str) -> std::io::Result<()> {
     write_default_yaml_with_method(path, "fft")
 }
 
-pub fn maybe_write_default_yaml(path: &str) {
-    println!(
-        "[INFO] There is no config file detected in the target path.
+/// Offer to write the default config file into `path`.
+pub fn maybe_write_default_yaml(path: &str, input_mode: InputMode) -> io::Result<()> {
+    match input_mode {
+        InputMode::Interactive => println!(
+            "[INFO] There is no config file detected in the target path.
        Type 'y' or 'yes' if you want to generate a default one, or anything else to quit."
-    );
+        ),
+        InputMode::NoInput => println!(
+            "[INFO] There is no config file detected in the target path.
+       Not generating a default one without input, use `--yes` or `psc init` to do so."
+        ),
+        InputMode::AssumeYes => {}
+    }
 
-    let mut input_text = String::new();
-    io::stdin()
-        .read_line(&mut input_text)
-        .expect("failed to read from stdin");
+    if input_mode.confirm()? {
+        write_default_yaml(path)?;
+        println!("[INFO] Created 'eval.yaml' config file.");
+    }
+    Ok(())
+}
 
-    match input_text.to_lowercase().trim() {
-        "yes" | "y" => {
-            let _r = write_default_yaml(path);
-            println!("[INFO] Created 'eval.yaml' config file.");
-        }
-        _ => {}
-    };
+#[test]
+fn template_selection_respects_version_ranges() {
+    assert_eq!(
+        "0.30.1.dev0".parse::<PysprintVersion>(),
+        Ok(PysprintVersion(0, 30, 1))
+    );
+    assert!(select_templates_for("0.12.0").is_err());
+    assert!(select_templates_for("0.31.0").is_err());
+    assert_eq!(
+        select_templates_for("0.30.0").ok(),
+        Some(Templates::default())
+    );
 }
@@ -0,0 +1,141 @@
+use crate::evaluator::FileResult;
+use crate::statistics::{collect_coefficients, read_results_from_file};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+/// A single row of the dashboard's history.
+#[derive(Debug, Clone)]
+struct EvaluatedFile {
+    name: String,
+    // GD, GDD and TOD, if the evaluation succeeded
+    coeffs: Option<[f64; 3]>,
+    failed: bool,
+}
+
+/// An in-place terminal dashboard for watch mode.
+///
+/// It keeps track of the last `history` evaluated files, and redraws the whole
+/// screen on every update.
+#[derive(Debug)]
+pub struct Dashboard {
+    history: usize,
+    recent: VecDeque<EvaluatedFile>,
+    evaluated: usize,
+    failed: usize,
+    last_error: Option<(String, String)>,
+    result_filepath: PathBuf,
+}
+
+impl Dashboard {
+    pub fn new<P: AsRef<Path>>(history: usize, result_filepath: P) -> Self {
+        Self {
+            history,
+            recent: VecDeque::with_capacity(history),
+            evaluated: 0,
+            failed: 0,
+            last_error: None,
+            result_filepath: result_filepath.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Record the outcome of an evaluation.
+    pub fn record(&mut self, result: &FileResult) {
+        self.evaluated += 1;
+        if let Some(error) = &result.error {
+            self.failed += 1;
+            self.last_error = Some((result.file.clone(), error.traceback.clone()));
+        }
+
+        if self.recent.len() == self.history {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(EvaluatedFile {
+            name: result.file.clone(),
+            coeffs: result.coefficients().map(|c| [c[0], c[1], c[2]]),
+            failed: !result.is_ok(),
+        });
+    }
+
+    pub fn render(
+        &self,
+        stdout: &mut StandardStream,
+        queue: &[String],
+        current: Option<&str>,
+    ) -> io::Result<()> {
+        // clear terminal and move the cursor to the top left corner
+        write!(stdout, "\x1B[2J\x1B[1;1H")?;
+
+        stdout.set_color(ColorSpec::new().set_bold(true))?;
+        writeln!(stdout, "PySprint watch dashboard")?;
+        WriteColor::reset(stdout)?;
+        writeln!(
+            stdout,
+            "{} evaluated, {} failed, watching {:?}",
+            self.evaluated,
+            self.failed,
+            self.result_filepath
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+        )?;
+        writeln!(stdout)?;
+
+        match current {
+            Some(name) => writeln!(stdout, "Evaluating: {}", name)?,
+            None => writeln!(stdout, "Evaluating: -")?,
+        }
+        if queue.is_empty() {
+            writeln!(stdout, "Queue:      idle")?;
+        } else {
+            writeln!(
+                stdout,
+                "Queue:      {} pending ({})",
+                queue.len(),
+                queue.join(", ")
+            )?;
+        }
+        writeln!(stdout)?;
+
+        stdout.set_color(ColorSpec::new().set_bold(true))?;
+        writeln!(
+            stdout,
+            "{:<32} {:>14} {:>14} {:>14}",
+            "file", "GD [fs]", "GDD [fs^2]", "TOD [fs^3]"
+        )?;
+        WriteColor::reset(stdout)?;
+        for entry in &self.recent {
+            if entry.failed {
+                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+                writeln!(stdout, "{:<32} {:>44}", entry.name, "failed")?;
+                WriteColor::reset(stdout)?;
+            } else if let Some([gd, gdd, tod]) = entry.coeffs {
+                writeln!(
+                    stdout,
+                    "{:<32} {:>14.5} {:>14.5} {:>14.5}",
+                    entry.name, gd, gdd, tod
+                )?;
+            } else {
+                writeln!(stdout, "{:<32} {:>44}", entry.name, "no result")?;
+            }
+        }
+        writeln!(stdout)?;
+
+        if let Ok(map) = read_results_from_file(&self.result_filepath) {
+            writeln!(stdout, "Summary of {} entries:", map.len())?;
+            for coeff in collect_coefficients(map.values()).iter().take(3) {
+                writeln!(stdout, "  {}", coeff)?;
+            }
+            writeln!(stdout)?;
+        }
+
+        if let Some((name, traceback)) = &self.last_error {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+            writeln!(stdout, "Last error ({}):", name)?;
+            WriteColor::reset(stdout)?;
+            writeln!(stdout, "{}", traceback)?;
+        }
+
+        stdout.flush()
+    }
+}
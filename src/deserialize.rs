@@ -1,81 +1,161 @@
-use serde::{de, Deserialize, Deserializer, Serialize};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, ObjectValidation, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
 use tera::{Context, Result as TeraResult};
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Config {
+    /// The version of the config file layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    /// How the files are collected and loaded.
     pub(crate) load_options: LoadOptions,
+    /// The preprocessing of the loaded files.
     preprocess: Preprocess,
-    #[serde(deserialize_with = "de_from_method_str")]
-    pub(crate) method: MethodType,
-    method_details: MethodDetails,
+    /// The method to evaluate with, and its options.
+    #[serde(flatten)]
+    pub(crate) method: Method,
+    /// Python code to run before the evaluation.
     #[serde(default = "default_trigger")]
+    #[schemars(default)]
     before_evaluate: Option<StringSequence>,
+    /// The options of the evaluation.
     evaluate: Option<Evaluate>,
+    /// Python code to run after the evaluation.
     #[serde(default = "default_trigger")]
+    #[schemars(default)]
     after_evaluate: Option<StringSequence>,
+    /// The time limit of evaluating a single file, in seconds.
+    timeout_seconds: Option<f64>,
+    /// The Python interpreter or environment to use.
+    python: Option<String>,
+    /// The values to evaluate with in a single `audit`, by their dotted key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sweep: Option<BTreeMap<String, SweepValues>>,
+    /// The name of the profile overlaid on the config, it's recorded in the results.
+    #[serde(skip)]
+    pub(crate) profile: Option<String>,
 }
 
 impl Config {
     pub fn insert_into_ctx(&self) -> TeraResult<Context> {
-        let mut ctx = Context::from_serialize(&self.load_options).unwrap();
-        let ctx2 = Context::from_serialize(&self.preprocess).unwrap();
-        let ctx3 = Context::from_serialize(&self.method_details).unwrap();
-        let ctx4 = Context::from_serialize(&self.evaluate).unwrap();
+        let mut ctx = Context::from_serialize(&self.load_options)?;
+        let ctx2 = Context::from_serialize(&self.preprocess)?;
+        let ctx3 = Context::from_serialize(self.method.details())?;
+        let ctx4 = Context::from_serialize(&self.evaluate)?;
         ctx.extend(ctx2);
         ctx.extend(ctx3);
         ctx.extend(ctx4);
         ctx.insert("bet", &self.before_evaluate);
         ctx.insert("aet", &self.after_evaluate);
-        ctx.insert("methodname", &self.method);
+        ctx.insert("methodname", &self.method.kind());
+        ctx.insert("profile", &self.profile);
         Ok(ctx)
     }
+
+    /// The Python interpreter or environment to use, if it's set.
+    pub fn python(&self) -> Option<&str> {
+        self.python.as_deref()
+    }
+
+    /// The time limit of a single evaluation. The method specific
+    /// setting takes precedence over the global one.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.method
+            .timeout_seconds()
+            .or(self.timeout_seconds)
+            .filter(|secs| *secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+    }
+
+    /// The method specific and evaluation parameters that are set, sorted by name.
+    pub fn parameters(&self) -> Vec<(String, serde_json::Value)> {
+        let set_fields = |value: serde_json::Value| match value {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        let mut parameters = set_fields(self.method.details());
+        parameters.extend(set_fields(
+            serde_json::to_value(&self.evaluate).unwrap_or_default(),
+        ));
+        parameters
+    }
+
+    /// Reject the options that contradict each other.
+    pub fn validate(&self) -> Result<(), String> {
+        check_timeout("timeout_seconds", self.timeout_seconds)?;
+        self.method.validate()
+    }
 }
 
-fn de_from_method_str<'de, D>(deserializer: D) -> Result<MethodType, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    match &*s {
-        "wft" => Ok(MethodType::WFTMethod),
-        "fft" => Ok(MethodType::FFTMethod),
-        "spp" => Ok(MethodType::SPPMethod),
-        "cff" => Ok(MethodType::CosFitMethod),
-        "mm" => Ok(MethodType::MinMaxMethod),
-        method_str => Err(format!("expected valid method name, found {}", method_str)),
+/// Reject the time limits a `Duration` can't hold. Zero and negative ones mean there's no limit.
+fn check_timeout(name: &str, secs: Option<f64>) -> Result<(), String> {
+    match secs {
+        Some(secs)
+            if !secs.is_finite() || (secs > 0.0 && Duration::try_from_secs_f64(secs).is_err()) =>
+        {
+            Err(format!(
+                "{} must be a finite number of seconds, found {:?}",
+                name, secs
+            ))
+        }
+        _ => Ok(()),
     }
-    .map_err(de::Error::custom)
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct LoadOptions {
+    /// The file extensions to pick up, `__noextension` picks up the files without one.
     pub(crate) extensions: StringSequence,
+    /// Wildcard patterns of the files to leave out.
     #[serde(default = "default_placeholder")]
+    #[schemars(default = "no_files")]
     pub(crate) exclude_patterns: StringSequence,
+    /// The files to leave out, with their extension.
     #[serde(default = "default_placeholder")]
+    #[schemars(default = "no_files")]
     pub(crate) skip_files: StringSequence,
+    /// The number of lines to skip in each file.
     skiprows: u32,
+    /// The length of the meta information at the top of each file.
     meta_len: u32,
+    /// The decimal separator of the files.
     decimal: char,
+    /// The delimiter of the files.
     delimiter: char,
+    /// How the files are grouped: 3 takes them as interferogram, sample and reference arm
+    /// triplets, -1 does the same without normalizing, 1 takes every file by itself.
     #[serde(rename = "mod")]
     #[serde(default)]
+    #[schemars(default = "default_mod")]
     pub(crate) _mod: Option<_Mod>,
+    /// Whether to evaluate the files whose comment asks to skip them.
     #[serde(default = "no_comment_check_default")]
     no_comment_check: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 struct Preprocess {
+    /// Whether to change the domain of the loaded files.
     chdomain: Option<bool>,
+    /// The unit of the loaded files.
     #[serde(default = "input_unit_default")]
     input_unit: Option<String>,
+    /// Values below this are discarded, in PHz.
     slice_start: Option<f64>,
+    /// Values above this are discarded, in PHz.
     slice_stop: Option<f64>,
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(PartialEq, Serialize, Deserialize, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Debug)]
 pub(crate) enum MethodType {
     CosFitMethod,
     FFTMethod,
@@ -84,29 +164,219 @@ pub(crate) enum MethodType {
     SPPMethod,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-struct MethodDetails {
+impl MethodType {
+    /// The name of the method, as it's given in the config file.
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::CosFitMethod => "cff",
+            Self::FFTMethod => "fft",
+            Self::WFTMethod => "wft",
+            Self::MinMaxMethod => "mm",
+            Self::SPPMethod => "spp",
+        }
+    }
+}
+
+/// The method to evaluate with, and the options that belong to it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "method", content = "method_details")]
+pub(crate) enum Method {
+    #[serde(rename = "cff")]
+    CosFit(BasicDetails),
+    #[serde(rename = "fft")]
+    Fft(BasicDetails),
+    #[serde(rename = "wft")]
+    Wft(WftDetails),
+    #[serde(rename = "mm")]
+    MinMax(MinMaxDetails),
+    #[serde(rename = "spp")]
+    Spp(SppDetails),
+}
+
+impl Method {
+    pub(crate) const fn kind(&self) -> MethodType {
+        match self {
+            Self::CosFit(_) => MethodType::CosFitMethod,
+            Self::Fft(_) => MethodType::FFTMethod,
+            Self::Wft(_) => MethodType::WFTMethod,
+            Self::MinMax(_) => MethodType::MinMaxMethod,
+            Self::Spp(_) => MethodType::SPPMethod,
+        }
+    }
+
+    /// The name of the method, as it's given in the config file.
+    pub(crate) const fn name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    /// The options of the method.
+    fn details(&self) -> serde_json::Value {
+        match self {
+            Self::CosFit(details) | Self::Fft(details) => serde_json::to_value(details),
+            Self::Wft(details) => serde_json::to_value(details),
+            Self::MinMax(details) => serde_json::to_value(details),
+            Self::Spp(details) => serde_json::to_value(details),
+        }
+        .unwrap_or_default()
+    }
+
+    const fn timeout_seconds(&self) -> Option<f64> {
+        match self {
+            Self::CosFit(details) | Self::Fft(details) => details.timeout_seconds,
+            Self::Wft(details) => details.timeout_seconds,
+            Self::MinMax(details) => details.timeout_seconds,
+            Self::Spp(details) => details.timeout_seconds,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check_timeout("method_details.timeout_seconds", self.timeout_seconds())?;
+        match self {
+            Self::Wft(details) => {
+                if details.fwhm.is_some() && details.std.is_some() {
+                    return Err(String::from(
+                        "the window is set by either method_details.fwhm or method_details.std, not both",
+                    ));
+                }
+                if let Some(windows) = details.windows.filter(|windows| *windows < 1) {
+                    return Err(format!(
+                        "method_details.windows must be positive, found {}",
+                        windows
+                    ));
+                }
+                for (name, width) in [("fwhm", details.fwhm), ("std", details.std)] {
+                    if let Some(width) = width.filter(|width| *width <= 0.0) {
+                        return Err(format!(
+                            "method_details.{} must be positive, found {}",
+                            name, width
+                        ));
+                    }
+                }
+            }
+            Self::MinMax(details) => {
+                let sides = [
+                    ("min", details.min),
+                    ("max", details.max),
+                    ("both", details.both),
+                ]
+                .iter()
+                .filter(|(_, side)| *side == Some(true))
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>();
+                if sides.len() > 1 {
+                    return Err(format!(
+                        "only one of method_details.min, max and both can be true, found {}",
+                        sides.join(", ")
+                    ));
+                }
+            }
+            Self::CosFit(_) | Self::Fft(_) | Self::Spp(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// The options of the methods without their own ones.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BasicDetails {
+    /// Whether to show the interferogram before the evaluation.
+    plot: Option<bool>,
+    /// The time limit of evaluating a single file, overriding the global one.
+    timeout_seconds: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WftDetails {
+    /// Whether to show the interferogram before the evaluation.
+    plot: Option<bool>,
+    /// The time limit of evaluating a single file, overriding the global one.
+    timeout_seconds: Option<f64>,
+    /// Whether to show the heatmap after the evaluation.
     heatmap: Option<bool>,
+    /// The number of windows to apply on the interferogram, 300 by default.
     windows: Option<i32>,
+    /// The full width at half maximum of the window function in PHz, 0.05 by default.
     fwhm: Option<f64>,
+    /// The standard deviation of the window function in PHz, instead of the `fwhm`.
     std: Option<f64>,
+    /// Whether to evaluate the windows in parallel with Dask.
     parallel: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MinMaxDetails {
+    /// Whether to show the interferogram before the evaluation.
     plot: Option<bool>,
+    /// The time limit of evaluating a single file, overriding the global one.
+    timeout_seconds: Option<f64>,
+    /// Whether to use only the minimums.
     min: Option<bool>,
+    /// Whether to use only the maximums.
     max: Option<bool>,
+    /// Whether to use both the minimums and maximums, it's the default.
     both: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SppDetails {
+    /// Whether to show the interferogram before the evaluation.
+    plot: Option<bool>,
+    /// The time limit of evaluating a single file, overriding the global one.
+    timeout_seconds: Option<f64>,
+    /// Whether to calculate on every SPP recording.
     eager: Option<bool>,
+    /// Whether to open the interactive SPP editor window.
     detach: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 struct Evaluate {
+    /// The reference frequency in PHz.
     reference_frequency: Option<f64>,
+    /// The maximum order of dispersion to look for, from 2 to 6.
     order: Option<u32>,
+    /// Whether to only retrieve the phase, without fitting a curve.
     only_phase: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// The values a parameter takes during a sweep.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum SweepValues {
+    List(#[schemars(with = "Vec<serde_json::Value>")] Vec<serde_yaml::Value>),
+    /// From `start` to `stop` inclusive.
+    Range {
+        start: f64,
+        stop: f64,
+        step: f64,
+    },
+}
+
+impl SweepValues {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn values(&self) -> Vec<serde_yaml::Value> {
+        match self {
+            Self::List(values) => values.clone(),
+            Self::Range { start, stop, step } if *step > 0.0 && start <= stop => {
+                // a little slack, so the floating point error doesn't leave out `stop`
+                let steps = ((stop - start) / step + 1e-9).floor() as usize;
+                (0..=steps)
+                    .map(|i| {
+                        let value = (i as f64).mul_add(*step, *start);
+                        serde_yaml::Value::from((value * 1e9).round() / 1e9)
+                    })
+                    .collect()
+            }
+            Self::Range { .. } => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum StringSequence {
     Vector(Vec<String>),
@@ -139,6 +409,21 @@ impl IntoIterator for StringSequence {
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct _Mod(pub i32);
 
+impl JsonSchema for _Mod {
+    fn schema_name() -> String {
+        String::from("Mod")
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Integer.into()),
+            enum_values: Some(vec![(-1).into(), 1.into(), 3.into()]),
+            ..SchemaObject::default()
+        }
+        .into()
+    }
+}
+
 impl Default for _Mod {
     fn default() -> Self {
         Self(1)
@@ -171,3 +456,234 @@ fn default_trigger() -> Option<StringSequence> {
 fn default_placeholder() -> StringSequence {
     StringSequence::String(String::from("default_features"))
 }
+
+/// A missing `mod` is taken as 1.
+fn default_mod() -> Option<_Mod> {
+    Some(_Mod::default())
+}
+
+/// The default of [`default_placeholder`] fields, as it's seen by the user.
+const fn no_files() -> StringSequence {
+    StringSequence::Vector(vec![])
+}
+
+/// The JSON Schema of the config file, for editors to complete and validate it with.
+pub fn config_schema() -> serde_json::Value {
+    let mut schema = schemars::schema_for!(Config);
+    let metadata = schema.schema.metadata();
+    metadata.title = Some(String::from("eval.yaml"));
+    // it's flattened from the method
+    metadata.description = None;
+    // the method's defaults are taken if the `method_details` are left out, and the
+    // required keys may come from the extended file, so nothing is required of a single file
+    schema.schema.object().required.clear();
+    let subschemas = schema.schema.subschemas();
+    // without a `method`, every variant matches
+    subschemas.any_of = subschemas.one_of.take();
+    for variant in subschemas.any_of.iter_mut().flatten() {
+        if let Schema::Object(variant) = variant {
+            variant.object().required.clear();
+        }
+    }
+    // these are resolved before the config is deserialized, so they're not part of it
+    let properties = &mut schema.schema.object().properties;
+    properties.insert(
+        String::from("extends"),
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            ..SchemaObject::default()
+        }
+        .into(),
+    );
+    properties.insert(
+        String::from("profiles"),
+        SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                additional_properties: Some(Box::new(Schema::Bool(true))),
+                ..ObjectValidation::default()
+            })),
+            ..SchemaObject::default()
+        }
+        .into(),
+    );
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+/// The version of the config file layout. Files without a version are of version 1.
+pub const CONFIG_VERSION: u64 = 2;
+
+/// Upgrades the layout of a version to the next one, and describes the changes it made.
+type Migration = fn(&mut Mapping) -> Vec<String>;
+
+/// The migrations by the version they upgrade, in order.
+const MIGRATIONS: [(u64, Migration); 1] = [(1, migrate_v1)];
+
+/// Upgrade the raw config file to the current layout, and set its version. Returns the
+/// changes made to it, which is empty if only the version was set.
+pub fn migrate(raw: &mut Value) -> Result<Vec<String>, String> {
+    let Value::Mapping(map) = raw else {
+        return Ok(vec![]);
+    };
+    let version_key = Value::from("version");
+    let version = match map.get(&version_key) {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version > 0)
+            .ok_or_else(|| format!("expected a positive integer version, found {:?}", version))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "the config is of version {}, but only versions up to {} are supported, consider upgrading psc",
+            version, CONFIG_VERSION
+        ));
+    }
+
+    let mut changes = vec![];
+    for (from, migration) in MIGRATIONS {
+        if from >= version {
+            changes.extend(migration(map));
+        }
+    }
+    // the version goes first, as it decides how to read the rest
+    let rest = std::mem::take(map)
+        .into_iter()
+        .filter(|(key, _)| *key != version_key);
+    *map = std::iter::once((version_key.clone(), Value::from(CONFIG_VERSION)))
+        .chain(rest)
+        .collect();
+    Ok(changes)
+}
+
+/// Before versioning, `method_details` took the options of every method, and the ones the
+/// method doesn't use were ignored. They're dropped, in the profiles too.
+fn migrate_v1(map: &mut Mapping) -> Vec<String> {
+    let method = map
+        .get(&Value::from("method"))
+        .and_then(Value::as_str)
+        .map(String::from);
+    let mut changes = drop_foreign_details(map, method.as_deref(), "");
+
+    if let Some(Value::Mapping(profiles)) = map.get_mut(&Value::from("profiles")) {
+        for (name, profile) in profiles.iter_mut() {
+            let Value::Mapping(profile) = profile else {
+                continue;
+            };
+            let own = profile
+                .get(&Value::from("method"))
+                .and_then(Value::as_str)
+                .map(String::from);
+            let prefix = format!("profiles.{}.", name.as_str().unwrap_or_default());
+            changes.extend(drop_foreign_details(
+                profile,
+                own.as_deref().or(method.as_deref()),
+                &prefix,
+            ));
+        }
+    }
+    changes
+}
+
+/// The `method_details` of version 1, shared by every method. `only_phase` was written
+/// there by the default config, though only `evaluate` took it.
+const SHARED_DETAILS: [&str; 11] = [
+    "heatmap",
+    "windows",
+    "fwhm",
+    "std",
+    "parallel",
+    "min",
+    "max",
+    "both",
+    "eager",
+    "detach",
+    "only_phase",
+];
+
+/// Drop the `method_details` of version 1 that `method` doesn't take. They're left alone
+/// if the method is unknown, e.g. when it's set by the file this one extends, and so are
+/// the unknown keys, to be rejected when the config is read.
+fn drop_foreign_details(map: &mut Mapping, method: Option<&str>, prefix: &str) -> Vec<String> {
+    let own: &[&str] = match method {
+        Some("cff" | "fft") => &[],
+        Some("wft") => &["heatmap", "windows", "fwhm", "std", "parallel"],
+        Some("mm") => &["min", "max", "both"],
+        Some("spp") => &["eager", "detach"],
+        _ => return vec![],
+    };
+    let Some(Value::Mapping(details)) = map.get_mut(&Value::from("method_details")) else {
+        return vec![];
+    };
+    let mut changes = vec![];
+
+    // the template took both sides if min and max were both set
+    let is_set = |details: &Mapping, key: &str| {
+        details.get(&Value::from(key)).and_then(Value::as_bool) == Some(true)
+    };
+    if method == Some("mm") && is_set(details, "min") && is_set(details, "max") {
+        details.remove(&Value::from("min"));
+        details.remove(&Value::from("max"));
+        details.insert(Value::from("both"), Value::from(true));
+        changes.push(format!(
+            "replaced {0}method_details.min and max with {0}method_details.both",
+            prefix
+        ));
+    }
+
+    let dropped = details
+        .iter()
+        .filter_map(|(key, _)| key.as_str())
+        .filter(|key| SHARED_DETAILS.contains(key) && !own.contains(key))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    for key in &dropped {
+        details.remove(&Value::from(key.as_str()));
+    }
+    if !dropped.is_empty() {
+        changes.push(format!(
+            "dropped {}method_details.{}, {} doesn't use them",
+            prefix,
+            dropped.join(", "),
+            method.unwrap_or_default()
+        ));
+    }
+    changes
+}
+
+#[test]
+fn schema_file_is_up_to_date() {
+    let written: serde_json::Value =
+        serde_json::from_str(include_str!("../eval.schema.json")).unwrap();
+    assert!(
+        written == config_schema(),
+        "eval.schema.json is outdated, regenerate it with `psc config schema > eval.schema.json`"
+    );
+}
+
+#[test]
+fn method_details_belong_to_their_method() {
+    let method = |yaml: &str| serde_yaml::from_str::<Method>(yaml).map_err(|e| e.to_string());
+    assert_eq!(
+        method("method: fft\nmethod_details: {}").unwrap().kind(),
+        MethodType::FFTMethod
+    );
+    assert!(method("method: fft\nmethod_details: { windows: 200 }").is_err());
+    assert!(method("method: spp\nmethod_details: { eager: true, detach: false }").is_ok());
+    // only the spp template opens an editor
+    assert!(method("method: mm\nmethod_details: { detach: true }").is_err());
+
+    let conflicts = [
+        "method: wft\nmethod_details: { fwhm: 0.05, std: 0.05 }",
+        "method: wft\nmethod_details: { windows: 0 }",
+        "method: wft\nmethod_details: { std: -0.1 }",
+        "method: mm\nmethod_details: { min: true, max: true }",
+        "method: fft\nmethod_details: { timeout_seconds: .inf }",
+        "method: fft\nmethod_details: { timeout_seconds: 1e300 }",
+    ];
+    for yaml in conflicts {
+        assert!(method(yaml).unwrap().validate().is_err(), "{}", yaml);
+    }
+    let sides = method("method: mm\nmethod_details: { min: true, max: false, both: false }");
+    assert!(sides.unwrap().validate().is_ok());
+}
@@ -0,0 +1,259 @@
+use crate::interpreter::{embedded_version, requested_interpreter, resolve, PythonInterpreter};
+use crate::python::{eval_py_variable, prepare_python};
+use crate::utils::{EXIT_FAILURE, EXIT_PYTHON_ERROR, EXIT_SUCCESS};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::Command;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+/// Collects everything we need to know about the Python environment
+/// into the `report` variable, as a JSON string.
+const CHECK: &str = r#"import json, os, sys
+report = {
+    "version": sys.version.split()[0],
+    "prefix": sys.prefix,
+    "pythonhome": os.environ.get("PYTHONHOME"),
+    "modules": {},
+    "matplotlib_backend": None,
+    "fragment_api": {},
+}
+for name in ("numpy", "pysprint", "matplotlib"):
+    try:
+        module = __import__(name)
+        report["modules"][name] = {"version": getattr(module, "__version__", "unknown"), "error": None}
+    except Exception as e:
+        report["modules"][name] = {"version": None, "error": f"{type(e).__name__}: {e}"}
+try:
+    import matplotlib
+    report["matplotlib_backend"] = matplotlib.get_backend()
+except Exception:
+    pass
+try:
+    import pysprint.utils
+    for name in ("_prepare_json_fragment", "_write_or_update_json_fragment"):
+        report["fragment_api"][name] = callable(getattr(pysprint.utils, name, None))
+except Exception:
+    pass
+report = json.dumps(report)
+"#;
+
+const MODULES: [&str; 3] = ["numpy", "pysprint", "matplotlib"];
+
+#[derive(Debug, Deserialize)]
+struct ModuleReport {
+    version: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    version: String,
+    prefix: String,
+    pythonhome: Option<String>,
+    modules: BTreeMap<String, ModuleReport>,
+    matplotlib_backend: Option<String>,
+    fragment_api: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Prints the outcome of the checks, and keeps count of the problems.
+struct Checklist<'a> {
+    stdout: &'a mut StandardStream,
+    problems: usize,
+}
+
+impl Checklist<'_> {
+    fn check(&mut self, status: Status, message: &str) {
+        let (label, color) = match status {
+            Status::Ok => ("[ OK ]", Color::Green),
+            Status::Warn => ("[WARN]", Color::Yellow),
+            Status::Fail => ("[FAIL]", Color::Red),
+        };
+        if status == Status::Fail {
+            self.problems += 1;
+        }
+        let _ = self.stdout.set_color(ColorSpec::new().set_fg(Some(color)));
+        let _ = write!(self.stdout, "{}", label);
+        let _ = WriteColor::reset(self.stdout);
+        let _ = writeln!(self.stdout, " {}", message);
+    }
+
+    fn suggest(&mut self, fix: &str) {
+        let _ = writeln!(self.stdout, "       fix: {}", fix);
+    }
+}
+
+fn run_check(
+    interpreter: Option<&PythonInterpreter>,
+    backend: &str,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let report = if backend == "subprocess" {
+        let executable = &interpreter.ok_or("no Python interpreter found")?.executable;
+        let output = Command::new(executable)
+            .args(["-c", &format!("{}\nprint(report)", CHECK)])
+            .output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+        }
+        String::from_utf8(output.stdout)?
+    } else {
+        prepare_python(interpreter)?;
+        eval_py_variable(CHECK, "report")?
+    };
+    Ok(serde_json::from_str(&report)?)
+}
+
+/// Check whether the environment is able to run evaluations, and suggest fixes if it
+/// isn't. Returns the process exit code, which is non-zero if there are problems.
+pub fn doctor(
+    stdout: &mut StandardStream,
+    python: Option<&str>,
+    config_python: Option<&str>,
+    backend: &str,
+) -> i32 {
+    let mut checklist = Checklist {
+        stdout,
+        problems: 0,
+    };
+
+    checklist.check(
+        Status::Ok,
+        &format!(
+            "psc is built against Python {}, using the {} backend",
+            embedded_version(),
+            backend
+        ),
+    );
+
+    let requested = requested_interpreter(python, config_python);
+    let explicit = requested.is_some();
+    let interpreter = match resolve(requested) {
+        Ok(interpreter) => {
+            checklist.check(Status::Ok, &format!("found {}", interpreter));
+            if backend == "embedded" {
+                if interpreter.matches_embedded() {
+                    checklist.check(
+                        Status::Ok,
+                        &format!("PYTHONHOME will be set to {:?}", interpreter.home),
+                    );
+                } else {
+                    checklist.check(
+                        Status::Warn,
+                        &format!(
+                            "the interpreter's version doesn't match Python {}, PYTHONHOME is left unset and its packages are not loaded",
+                            embedded_version()
+                        ),
+                    );
+                    checklist.suggest("use `--backend subprocess`, or select a matching interpreter with `--python`");
+                }
+            }
+            Some(interpreter)
+        }
+        Err(e) => {
+            let status = if explicit || backend == "subprocess" {
+                Status::Fail
+            } else {
+                Status::Warn
+            };
+            checklist.check(status, &e.to_string());
+            checklist.suggest(
+                "install Python 3.6+, or point psc to one with `--python` or `PSC_PYTHON`",
+            );
+            None
+        }
+    };
+
+    let report = match run_check(interpreter.as_ref(), backend) {
+        Ok(report) => report,
+        Err(e) => {
+            checklist.check(Status::Fail, &format!("failed to run Python: {}", e));
+            checklist.suggest("check the interpreter above, or try the other `--backend`");
+            return EXIT_PYTHON_ERROR;
+        }
+    };
+
+    checklist.check(
+        Status::Ok,
+        &format!(
+            "Python {} is running from {:?}, the PYTHONHOME environment variable is {}",
+            report.version,
+            report.prefix,
+            report.pythonhome.as_deref().unwrap_or("unset")
+        ),
+    );
+
+    let pip = interpreter
+        .as_ref()
+        .map_or_else(|| String::from("python"), |i| format!("{:?}", i.executable));
+    for name in MODULES {
+        match report.modules.get(name) {
+            Some(ModuleReport {
+                version: Some(version),
+                ..
+            }) => checklist.check(Status::Ok, &format!("{} {}", name, version)),
+            Some(ModuleReport { error, .. }) => {
+                checklist.check(
+                    Status::Fail,
+                    &format!(
+                        "{} can't be imported: {}",
+                        name,
+                        error.as_deref().unwrap_or("unknown error")
+                    ),
+                );
+                checklist.suggest(&format!("{} -m pip install {}", pip, name));
+            }
+            None => checklist.check(Status::Fail, &format!("{} was not checked", name)),
+        }
+    }
+
+    match report.matplotlib_backend.as_deref() {
+        Some(mpl_backend) if mpl_backend.eq_ignore_ascii_case("agg") => {
+            checklist.check(
+                Status::Warn,
+                &format!("matplotlib backend is {}, plots won't show up", mpl_backend),
+            );
+            checklist.suggest("set the MPLBACKEND environment variable, e.g. to TkAgg or QtAgg");
+        }
+        Some(mpl_backend) => {
+            checklist.check(
+                Status::Ok,
+                &format!("matplotlib backend is {}", mpl_backend),
+            );
+        }
+        None => {}
+    }
+
+    if report
+        .modules
+        .get("pysprint")
+        .is_some_and(|m| m.error.is_none())
+    {
+        for (name, present) in &report.fragment_api {
+            if *present {
+                checklist.check(Status::Ok, &format!("ps.utils.{} exists", name));
+            } else {
+                checklist.check(Status::Fail, &format!("ps.utils.{} is missing", name));
+                checklist.suggest(&format!("{} -m pip install -U pysprint", pip));
+            }
+        }
+    }
+
+    if checklist.problems > 0 {
+        let _ = writeln!(
+            checklist.stdout,
+            "\n{} problem(s) found.",
+            checklist.problems
+        );
+        EXIT_FAILURE
+    } else {
+        let _ = writeln!(checklist.stdout, "\nNo problems found.");
+        EXIT_SUCCESS
+    }
+}
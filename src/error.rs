@@ -0,0 +1,89 @@
+use crate::utils::{EXIT_CONFIG_ERROR, EXIT_FAILURE, EXIT_PYTHON_ERROR};
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while evaluating.
+#[derive(Debug)]
+pub enum Error {
+    /// The config file or the command line options are missing or invalid.
+    Config(String),
+    /// Reading or writing the data files, logs or reports failed.
+    Io(io::Error),
+    /// The Python code couldn't be generated from the templates.
+    Template(tera::Error),
+    /// Python or pysprint couldn't be initialized, or failed to run the code.
+    Python(String),
+    /// The result file couldn't be created or read.
+    ResultFile(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The process exit code belonging to this error.
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Config(_) | Self::ResultFile(_) => EXIT_CONFIG_ERROR,
+            Self::Python(_) => EXIT_PYTHON_ERROR,
+            Self::Io(_) | Self::Template(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(message) => write!(f, "invalid configuration: {}", message),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Template(e) => {
+                // tera keeps the useful part of the message in the source
+                write!(f, "failed to generate the Python code: {}", e)?;
+                let mut source = std::error::Error::source(e);
+                while let Some(e) = source {
+                    write!(f, ": {}", e)?;
+                    source = e.source();
+                }
+                Ok(())
+            }
+            Self::Python(message) => write!(f, "{}", message),
+            Self::ResultFile(message) => write!(f, "result file error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Template(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<tera::Error> for Error {
+    fn from(e: tera::Error) -> Self {
+        Self::Template(e)
+    }
+}
+
+impl From<pyo3::PyErr> for Error {
+    fn from(e: pyo3::PyErr) -> Self {
+        Self::Python(e.to_string())
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        match e {
+            notify::Error::Io(e) => Self::Io(e),
+            other => Self::Io(io::Error::other(other.to_string())),
+        }
+    }
+}
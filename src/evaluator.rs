@@ -0,0 +1,722 @@
+use crate::backend::{Backend, EmbeddedBackend, Execution};
+use crate::codegen::{
+    render_generic_template, render_spp_template, write_tempfile_with_imports, Templates,
+};
+use crate::deserialize::{_Mod, Config, MethodType};
+use crate::error::{Error, Result};
+use crate::interrupt::{cancelled, interrupted};
+use crate::io::{create_results_file, get_files, write_log, write_results};
+use crate::report::{ErrorEntry, ErrorReport};
+use crate::statistics::{entry_coefficients, read_results_from_file};
+use crate::utils::{sort_by_arms, RunSummary};
+use itertools::izip;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use serde_json::{json, Map, Value};
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+use tempfile::{Builder, NamedTempFile};
+
+/// Something that happened during [`Evaluator::run`] or [`Evaluator::watch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Not fatal, but probably worth a look.
+    Warning(String),
+    /// The files are selected, and `jobs` evaluations are about to run.
+    /// Both are empty in watch mode, where they're not known upfront.
+    Started { files: Vec<PathBuf>, jobs: usize },
+    /// The files waiting to be evaluated in watch mode changed.
+    Queued(Vec<String>),
+    /// The evaluation of `file` is starting. `index` counts from 0.
+    Evaluating { file: String, index: usize },
+    /// The evaluation of a file finished.
+    Evaluated(Box<FileResult>),
+    /// Every evaluation finished, or the run was interrupted.
+    Finished(RunSummary),
+}
+
+/// The outcome of evaluating a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileResult {
+    /// The name of the file, as it appears in the result file and the logs.
+    pub file: String,
+    /// The entry of the file in the result file, if there's one.
+    pub entry: Option<Value>,
+    /// Why the evaluation failed, if it did.
+    pub error: Option<ErrorEntry>,
+    /// Where the output of the evaluation was saved.
+    pub log: Option<PathBuf>,
+}
+
+impl FileResult {
+    pub const fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The GD, GDD, TOD, FOD, QOD and SOD values, if the evaluation succeeded.
+    pub fn coefficients(&self) -> Option<[f64; 6]> {
+        self.entry
+            .as_ref()
+            .filter(|_| self.is_ok())
+            .map(entry_coefficients)
+    }
+}
+
+/// Everything a run produced.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Evaluation {
+    /// The results in the order the files were evaluated.
+    pub results: Vec<FileResult>,
+    pub summary: RunSummary,
+}
+
+impl Evaluation {
+    /// The failed evaluations, ready to be written as a report.
+    pub fn error_report(&self) -> ErrorReport {
+        ErrorReport {
+            errors: self
+                .results
+                .iter()
+                .filter_map(|result| result.error.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Evaluates a set of files according to a config, without the command line.
+/// Set it up with the builder methods, and start it with [`Evaluator::run`].
+pub struct Evaluator<'a> {
+    config: Config,
+    workdir: String,
+    result_file: String,
+    files: Option<Vec<PathBuf>>,
+    verbosity: u8,
+    persist: bool,
+    backend: Box<dyn Backend + 'a>,
+    templates: Templates,
+    is_audit: bool,
+    on_event: Box<dyn FnMut(Event) + 'a>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Evaluate the files of the current directory selected by `config`,
+    /// with the embedded Python interpreter.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            workdir: String::from("."),
+            result_file: String::from("results.json"),
+            files: None,
+            verbosity: 0,
+            persist: false,
+            backend: Box::new(EmbeddedBackend::default()),
+            templates: Templates::default(),
+            is_audit: true,
+            on_event: Box::new(|_| {}),
+        }
+    }
+
+    /// The directory of the data files. The result file, the logs and the
+    /// persisted code are written here as well.
+    #[must_use]
+    pub fn workdir(mut self, workdir: impl Into<String>) -> Self {
+        self.workdir = workdir.into();
+        self
+    }
+
+    /// The name of the result file in the working directory. It's created if it doesn't exist.
+    #[must_use]
+    pub fn result_file(mut self, result_file: impl Into<String>) -> Self {
+        self.result_file = result_file.into();
+        self
+    }
+
+    /// Evaluate exactly these files from the working directory,
+    /// instead of the ones selected by the config.
+    #[must_use]
+    pub fn files<I, P>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.files = Some(files.into_iter().map(Into::into).collect());
+        self
+    }
+
+    #[must_use]
+    pub const fn verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Keep the generated code next to the data files.
+    #[must_use]
+    pub const fn persist(mut self, persist: bool) -> Self {
+        self.persist = persist;
+        self
+    }
+
+    /// Run the evaluations with `backend`. Its handshake is done by [`Evaluator::run`].
+    #[must_use]
+    pub fn backend(mut self, backend: Box<dyn Backend + 'a>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Call `on_event` with the progress of the run.
+    #[must_use]
+    pub fn on_event(mut self, on_event: impl FnMut(Event) + 'a) -> Self {
+        self.on_event = Box::new(on_event);
+        self
+    }
+
+    /// Send the progress of the run through a channel, instead of calling back.
+    #[must_use]
+    pub fn events(self, sender: std::sync::mpsc::Sender<Event>) -> Self {
+        self.on_event(move |event| {
+            let _ = sender.send(event);
+        })
+    }
+
+    /// Evaluate every file, and return what happened to each of them.
+    /// Failed evaluations don't make this fail, they're recorded in the results.
+    pub fn run(mut self) -> Result<Evaluation> {
+        let result_filepath = self.prepare()?;
+        let files = match self.files.take() {
+            Some(files) => files,
+            None => get_files(&self.workdir, &self.config.load_options)?,
+        };
+        let evaluation = match self.config.method.kind() {
+            MethodType::SPPMethod => self.run_spp(files, &result_filepath)?,
+            _ => self.run_generic(files, &result_filepath)?,
+        };
+        (self.on_event)(Event::Finished(evaluation.summary.clone()));
+        Ok(evaluation)
+    }
+
+    /// Evaluate a single interferogram from the working directory, with the sample and
+    /// reference arms if they're given, regardless of `mod` and the file selection of the config.
+    pub fn run_file(
+        mut self,
+        file: impl Into<PathBuf>,
+        sam_: Option<PathBuf>,
+        ref_: Option<PathBuf>,
+    ) -> Result<Evaluation> {
+        if self.config.method.kind() == MethodType::SPPMethod {
+            return Err(Error::Config(String::from(
+                "the spp method evaluates every file at once, it can't be run on a single file",
+            )));
+        }
+        let result_filepath = self.prepare()?;
+        let job = (file.into(), sam_, ref_);
+        let files = std::iter::once(&job.0)
+            .chain(&job.1)
+            .chain(&job.2)
+            .cloned()
+            .collect();
+        let evaluation = self.run_jobs(files, &[job], &result_filepath)?;
+        (self.on_event)(Event::Finished(evaluation.summary.clone()));
+        Ok(evaluation)
+    }
+
+    /// Evaluate the files of the working directory whenever they're written or created,
+    /// until the run is interrupted. Every file is evaluated by itself, regardless of `mod`.
+    pub fn watch(mut self) -> Result<RunSummary> {
+        if matches!(
+            self.config.method.kind(),
+            MethodType::CosFitMethod | MethodType::SPPMethod
+        ) {
+            return Err(Error::Config(String::from(
+                "CosFitMethod and SPPMethod are not supported in watch mode.",
+            )));
+        }
+        let result_filepath = self.prepare()?;
+        self.is_audit = false;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = watcher(tx, Duration::from_millis(100))?;
+        watcher.watch(&self.workdir, RecursiveMode::NonRecursive)?;
+
+        let scratch = self.scratch()?;
+        let extensions = self
+            .config
+            .load_options
+            .extensions
+            .clone()
+            .into_comparable();
+        let mut queue = VecDeque::<PathBuf>::new();
+        let mut summary = RunSummary::default();
+        (self.on_event)(Event::Started {
+            files: vec![],
+            jobs: 0,
+        });
+
+        'watching: while !interrupted() {
+            // wake up regularly, so we notice when we're asked to stop
+            match rx.recv_timeout(Duration::from_millis(200)) {
+                Ok(event) => {
+                    if enqueue(&mut queue, event, &extensions) {
+                        (self.on_event)(Event::Queued(queued_names(&queue)));
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(notify::Error::Generic(String::from(
+                        "the watcher disconnected unexpectedly",
+                    ))
+                    .into());
+                }
+            }
+
+            while let Some(file) = queue.pop_front() {
+                if interrupted() {
+                    break 'watching;
+                }
+                // pick up everything that happened while we were busy
+                while let Ok(event) = rx.try_recv() {
+                    enqueue(&mut queue, event, &extensions);
+                }
+                (self.on_event)(Event::Queued(queued_names(&queue)));
+
+                let Some(filename) = file.file_name().and_then(OsStr::to_str) else {
+                    continue;
+                };
+                (self.on_event)(Event::Evaluating {
+                    file: filename.to_owned(),
+                    index: summary.processed,
+                });
+                let Some(result) = self.evaluate(
+                    &file,
+                    filename,
+                    None,
+                    None,
+                    scratch.path(),
+                    &result_filepath,
+                )?
+                else {
+                    break 'watching;
+                };
+                summary.processed += 1;
+                if let Some(error) = &result.error {
+                    summary.failed += 1;
+                    *summary.failures.entry(error.category).or_insert(0) += 1;
+                }
+                (self.on_event)(Event::Evaluated(Box::new(result)));
+            }
+        }
+        summary.interrupted = true;
+        (self.on_event)(Event::Finished(summary.clone()));
+        Ok(summary)
+    }
+
+    /// Do the handshake of the backend, and make sure the result file exists and is valid.
+    fn prepare(&mut self) -> Result<PathBuf> {
+        let handshake = self.backend.handshake()?;
+        for warning in handshake.warnings {
+            (self.on_event)(Event::Warning(warning));
+        }
+        self.templates = handshake.templates;
+
+        let result_filepath = Path::new(&self.workdir).join(&self.result_file);
+        if !result_filepath.exists() {
+            create_results_file(&result_filepath.to_string_lossy()).map_err(|e| {
+                Error::ResultFile(format!("failed to create {:?}: {}", result_filepath, e))
+            })?;
+        }
+        read_results_from_file(&result_filepath)?;
+        Ok(result_filepath)
+    }
+
+    /// The evaluations write their entry here first, so a cancelled one
+    /// can't leave a half-written result file behind.
+    fn scratch(&self) -> Result<NamedTempFile> {
+        Ok(Builder::new()
+            .prefix(".psc_entry")
+            .suffix(".json")
+            .tempfile_in(&self.workdir)?)
+    }
+
+    fn warn_if_arms_mismatch(&mut self, files: &[PathBuf]) {
+        let warn = matches!(
+            self.config.method.kind(),
+            MethodType::SPPMethod | MethodType::CosFitMethod | MethodType::MinMaxMethod
+        );
+        if warn && !files.len().is_multiple_of(3) {
+            (self.on_event)(Event::Warning(String::from(
+                "The number of files is not divisible by 3, it's very likely that this command will fail..
+       Maybe you forgot to exclude/include some?",
+            )));
+        }
+    }
+
+    /// All the files are evaluated at once.
+    fn run_spp(&mut self, files: Vec<PathBuf>, result_filepath: &Path) -> Result<Evaluation> {
+        let modulo = self.config.load_options._mod.unwrap_or_default();
+        self.warn_if_arms_mismatch(&files);
+        let Some((ifgs, sams, refs)) = spp_arms(&files, modulo) else {
+            return Err(invalid_mod(modulo));
+        };
+
+        let render = |result_file: &str| {
+            render_spp_template(
+                &self.templates,
+                &ifgs,
+                &refs,
+                &sams,
+                &self.workdir,
+                &self.config,
+                result_file,
+                self.verbosity,
+                true,
+            )
+        };
+        let scratch = self.scratch()?;
+        let code = render(scratch_name(scratch.path()))?;
+        if self.persist {
+            // the persisted code writes to the real result file
+            let _ =
+                write_tempfile_with_imports("spp_eval", &render(&self.result_file)?, &self.workdir);
+        }
+        create_results_file(&scratch.path().to_string_lossy())?;
+
+        (self.on_event)(Event::Started { files, jobs: 1 });
+        let name = "spp_eval";
+        (self.on_event)(Event::Evaluating {
+            file: name.to_owned(),
+            index: 0,
+        });
+        let execution = self
+            .backend
+            .execute(&code, self.config.timeout())
+            .unwrap_or_else(|e| failed_to_execute(&e));
+        let log = write_log(&self.workdir, name, &execution.output).ok();
+        let entry = read_results_from_file(scratch.path())
+            .ok()
+            .and_then(|mut results| results.remove(name));
+        // a cancelled evaluation leaves the result file alone
+        if let Some(entry) = entry.as_ref().filter(|_| !cancelled()) {
+            record(result_filepath, name, entry)?;
+        }
+        let result = FileResult {
+            file: name.to_owned(),
+            entry,
+            error: execution
+                .is_err
+                .then(|| ErrorEntry::new(name, &execution, &code, log.clone())),
+            log,
+        };
+        (self.on_event)(Event::Evaluated(Box::new(result.clone())));
+
+        let mut evaluation = Evaluation {
+            results: vec![result],
+            summary: RunSummary {
+                total: 1,
+                processed: 1,
+                interrupted: interrupted(),
+                ..RunSummary::default()
+            },
+        };
+        evaluation.summary.failed = usize::from(!evaluation.results[0].is_ok());
+        evaluation.summary.failures = evaluation.error_report().counts();
+        Ok(evaluation)
+    }
+
+    /// Every interferogram is evaluated on its own, with the sample and reference arms if we have them.
+    fn run_generic(&mut self, files: Vec<PathBuf>, result_filepath: &Path) -> Result<Evaluation> {
+        let modulo = self.config.load_options._mod.unwrap_or_default();
+        self.warn_if_arms_mismatch(&files);
+        let Some(jobs) = group_into_jobs(&files, modulo) else {
+            return Err(invalid_mod(modulo));
+        };
+        self.run_jobs(files, &jobs, result_filepath)
+    }
+
+    fn run_jobs(
+        &mut self,
+        files: Vec<PathBuf>,
+        jobs: &[Job],
+        result_filepath: &Path,
+    ) -> Result<Evaluation> {
+        let scratch = self.scratch()?;
+
+        let mut evaluation = Evaluation::default();
+        evaluation.summary.total = jobs.len();
+        (self.on_event)(Event::Started {
+            files,
+            jobs: jobs.len(),
+        });
+
+        for (index, (file, sam_, ref_)) in jobs.iter().enumerate() {
+            if interrupted() {
+                break;
+            }
+            let filename = file
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or("unknown filename");
+            (self.on_event)(Event::Evaluating {
+                file: filename.to_owned(),
+                index,
+            });
+            let Some(result) = self.evaluate(
+                file,
+                filename,
+                sam_.as_ref(),
+                ref_.as_ref(),
+                scratch.path(),
+                result_filepath,
+            )?
+            else {
+                break;
+            };
+            evaluation.summary.processed += 1;
+            if !result.is_ok() {
+                evaluation.summary.failed += 1;
+            }
+            (self.on_event)(Event::Evaluated(Box::new(result.clone())));
+            evaluation.results.push(result);
+        }
+        evaluation.summary.interrupted = interrupted();
+        evaluation.summary.failures = evaluation.error_report().counts();
+        Ok(evaluation)
+    }
+
+    /// Evaluate a single interferogram, and record its entry in the result file.
+    /// Returns `None` if the evaluation was cancelled.
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate(
+        &mut self,
+        file: &Path,
+        filename: &str,
+        sam_: Option<&PathBuf>,
+        ref_: Option<&PathBuf>,
+        scratch: &Path,
+        result_filepath: &Path,
+    ) -> Result<Option<FileResult>> {
+        let render = |result_file: &str| {
+            render_generic_template(
+                &self.templates,
+                filename,
+                &self.workdir,
+                &self.config,
+                result_file,
+                self.verbosity,
+                self.is_audit,
+                sam_,
+                ref_,
+            )
+        };
+        let code = render(scratch_name(scratch))?;
+        if self.persist {
+            // the persisted code writes to the real result file
+            let _ = write_tempfile_with_imports(
+                file.file_stem().and_then(OsStr::to_str).unwrap_or(filename),
+                &render(&self.result_file)?,
+                &self.workdir,
+            );
+        }
+        create_results_file(&scratch.to_string_lossy())?;
+
+        let timeout = self.config.timeout();
+        let execution = self
+            .backend
+            .execute(&code, timeout)
+            .unwrap_or_else(|e| failed_to_execute(&e));
+        if cancelled() {
+            return Ok(None);
+        }
+        let log = write_log(&self.workdir, filename, &execution.output).ok();
+
+        let mut error = None;
+        let mut entry = read_results_from_file(scratch)
+            .ok()
+            .and_then(|mut results| results.remove(filename));
+        if execution.is_err {
+            let mut error_entry = ErrorEntry::new(filename, &execution, &code, log.clone());
+            if execution.timed_out {
+                let secs = timeout.unwrap_or_default().as_secs_f64();
+                error_entry.message = format!("evaluation timed out after {} seconds", secs);
+                let mut timed_out = json!({ "error": "timeout", "timeout_seconds": secs });
+                if let Some(profile) = &self.config.profile {
+                    timed_out["profile"] = json!(profile);
+                }
+                entry = Some(timed_out);
+            }
+            error = Some(error_entry);
+        }
+        if let Some(entry) = &entry {
+            record(result_filepath, filename, entry)?;
+        }
+
+        Ok(Some(FileResult {
+            file: filename.to_owned(),
+            entry,
+            error,
+            log,
+        }))
+    }
+}
+
+/// Record the `entry` of `key` in the result file. The file is read again every time,
+/// so the changes made to it in the meantime, e.g. while watching, are kept.
+fn record(result_filepath: &Path, key: &str, entry: &Value) -> Result<()> {
+    let mut results = read_results_from_file(result_filepath)?
+        .into_iter()
+        .collect::<Map<_, _>>();
+    results.insert(key.to_owned(), entry.clone());
+    write_results(result_filepath, &results)
+        .map_err(|e| Error::ResultFile(format!("failed to write {:?}: {}", result_filepath, e)))
+}
+
+/// The name of the scratch file, the templates write into the working directory.
+fn scratch_name(scratch: &Path) -> &str {
+    scratch
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+}
+
+/// Queue the path of a file system event, if it's a file we need to evaluate.
+/// Returns whether the queue changed.
+fn enqueue(queue: &mut VecDeque<PathBuf>, event: DebouncedEvent, extensions: &[String]) -> bool {
+    // only trigger on Write and Create events..
+    let (DebouncedEvent::Write(path) | DebouncedEvent::Create(path)) = event else {
+        return false;
+    };
+    // if there's no extension, we probably should do nothing
+    let tracked = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| extensions.iter().any(|e| e == ext));
+    if tracked && !queue.contains(&path) {
+        queue.push_back(path);
+        return true;
+    }
+    false
+}
+
+fn queued_names(queue: &VecDeque<PathBuf>) -> Vec<String> {
+    queue
+        .iter()
+        .filter_map(|path| path.file_name().and_then(OsStr::to_str))
+        .map(String::from)
+        .collect()
+}
+
+pub(crate) fn invalid_mod(modulo: _Mod) -> Error {
+    Error::Config(format!(
+        "mod field should be 3, 1 or -1, found {}",
+        modulo.0
+    ))
+}
+
+/// An interferogram to evaluate, with its sample and reference arms if we have them.
+pub(crate) type Job = (PathBuf, Option<PathBuf>, Option<PathBuf>);
+
+/// Arrange the files into the evaluations of the generic methods, according to `mod`.
+/// Returns `None` if `mod` is invalid.
+pub(crate) fn group_into_jobs(files: &[PathBuf], modulo: _Mod) -> Option<Vec<Job>> {
+    let (ifgs, sams, refs) = sort_by_arms(files);
+    match modulo {
+        _Mod(3) => Some(
+            izip!(ifgs, sams, refs)
+                .map(|(file, sam_, ref_)| (file, Some(sam_), Some(ref_)))
+                .collect(),
+        ),
+        _Mod(1) => Some(
+            files
+                .iter()
+                .map(|file| (file.clone(), None, None))
+                .collect(),
+        ),
+        _Mod(-1) => Some(ifgs.into_iter().map(|file| (file, None, None)).collect()),
+        _ => None,
+    }
+}
+
+/// The interferogram, sample and reference arms the SPP method is given, according to `mod`.
+/// Returns `None` if `mod` is invalid.
+pub(crate) fn spp_arms(
+    files: &[PathBuf],
+    modulo: _Mod,
+) -> Option<(Vec<PathBuf>, Vec<PathBuf>, Vec<PathBuf>)> {
+    let (ifgs, sams, refs) = sort_by_arms(files);
+    match modulo {
+        _Mod(3) => Some((ifgs, sams, refs)),
+        _Mod(1) => Some((files.to_vec(), vec![], vec![])),
+        _Mod(-1) => Some((ifgs, vec![], vec![])),
+        _ => None,
+    }
+}
+
+/// The backend couldn't even start the evaluation, record it as a failed one.
+fn failed_to_execute(error: &Error) -> Execution {
+    Execution {
+        is_err: true,
+        traceback: error.to_string(),
+        output: format!("{}\n", error),
+        ..Execution::default()
+    }
+}
+
+#[test]
+fn only_successful_results_have_coefficients() {
+    let ok = FileResult {
+        file: String::from("a.trt"),
+        entry: Some(json!({ "GD": 1.0, "GDD": "200.5" })),
+        error: None,
+        log: None,
+    };
+    assert_eq!(ok.coefficients(), Some([1.0, 200.5, 0.0, 0.0, 0.0, 0.0]));
+
+    let failed = FileResult {
+        file: String::from("b.trt"),
+        entry: Some(json!({ "error": "timeout", "timeout_seconds": 1.0 })),
+        error: Some(ErrorEntry::new(
+            "b.trt",
+            &failed_to_execute(&Error::Config(String::new())),
+            "",
+            None,
+        )),
+        log: None,
+    };
+    assert_eq!(failed.coefficients(), None);
+
+    let evaluation = Evaluation {
+        results: vec![ok, failed],
+        ..Evaluation::default()
+    };
+    assert_eq!(evaluation.error_report().errors.len(), 1);
+}
+
+#[test]
+fn unparsable_result_files_are_refused() {
+    struct NoPython;
+    impl Backend for NoPython {
+        fn handshake(&mut self) -> Result<crate::backend::Handshake> {
+            Ok(crate::backend::Handshake::default())
+        }
+        fn execute(&mut self, _: &str, _: Option<Duration>) -> Result<Execution> {
+            unreachable!("nothing is evaluated")
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "fft").unwrap();
+    let config = crate::parser::parse(&dir.path().join("eval.yaml").to_string_lossy()).unwrap();
+    let result_file = dir.path().join("results.json");
+    std::fs::write(&result_file, "{ \"a.trt\": ").unwrap();
+
+    let evaluation = Evaluator::new(config)
+        .workdir(root)
+        .backend(Box::new(NoPython))
+        .run();
+    assert!(matches!(evaluation, Err(Error::ResultFile(_))));
+    assert_eq!(
+        std::fs::read_to_string(&result_file).unwrap(),
+        "{ \"a.trt\": "
+    );
+}
@@ -0,0 +1,180 @@
+use crate::error::{Error, Result};
+use pyo3::ffi::Py_GetVersion;
+use serde::Deserialize;
+use std::ffi::CStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The environment variable to select the Python interpreter with.
+pub const PYTHON_ENV_VAR: &str = "PSC_PYTHON";
+
+/// Where the choice of the Python interpreter came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpreterSource {
+    CommandLine,
+    Environment,
+    Config,
+    Path,
+}
+
+impl fmt::Display for InterpreterSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandLine => write!(f, "--python"),
+            Self::Environment => write!(f, "{}", PYTHON_ENV_VAR),
+            Self::Config => write!(f, "the config file"),
+            Self::Path => write!(f, "PATH"),
+        }
+    }
+}
+
+/// What the interpreter tells about itself.
+#[derive(Debug, Deserialize)]
+struct InterpreterInfo {
+    version: String,
+    prefix: PathBuf,
+    base_prefix: PathBuf,
+    site_packages: Vec<PathBuf>,
+}
+
+const QUERY: &str = r#"import json, site, sys
+print(json.dumps({
+    "version": sys.version.split()[0],
+    "prefix": sys.prefix,
+    "base_prefix": getattr(sys, "base_prefix", sys.prefix),
+    "site_packages": site.getsitepackages() if hasattr(site, "getsitepackages") else [],
+}))"#;
+
+/// A Python installation, virtualenv or conda environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonInterpreter {
+    pub executable: PathBuf,
+    pub source: InterpreterSource,
+    pub version: String,
+    /// The root of the installation, this is what `PYTHONHOME` needs to be.
+    pub home: PathBuf,
+    /// The site-packages of the virtualenv, if that's what we're using.
+    /// Those are not found through `PYTHONHOME`.
+    pub site_packages: Vec<PathBuf>,
+}
+
+impl PythonInterpreter {
+    /// Whether this is the same Python version as the one `psc` was built against.
+    pub fn matches_embedded(&self) -> bool {
+        major_minor(&self.version) == major_minor(&embedded_version())
+    }
+}
+
+impl fmt::Display for PythonInterpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Python {} at {:?} (selected by {})",
+            self.version, self.executable, self.source
+        )
+    }
+}
+
+/// The version of the Python library linked into `psc`.
+pub fn embedded_version() -> String {
+    // SAFETY: `Py_GetVersion` returns a static string, and it's fine to call
+    // before the interpreter is initialized.
+    let version = unsafe { CStr::from_ptr(Py_GetVersion()) };
+    version
+        .to_string_lossy()
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn major_minor(version: &str) -> Vec<&str> {
+    version.split('.').take(2).collect()
+}
+
+/// The interpreter explicitly asked for, in order of precedence: the command line,
+/// the `PSC_PYTHON` environment variable and the config file.
+pub fn requested_interpreter(
+    cli: Option<&str>,
+    config: Option<&str>,
+) -> Option<(String, InterpreterSource)> {
+    cli.map(|p| (p.to_owned(), InterpreterSource::CommandLine))
+        .or_else(|| {
+            std::env::var(PYTHON_ENV_VAR)
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(|p| (p, InterpreterSource::Environment))
+        })
+        .or_else(|| config.map(|p| (p.to_owned(), InterpreterSource::Config)))
+}
+
+/// Find the Python executable inside `path`, which is either the executable itself,
+/// or the root of an installation, virtualenv or conda environment.
+pub fn find_executable<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    let path = path.as_ref();
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    [
+        "python.exe",
+        "Scripts/python.exe",
+        "bin/python",
+        "bin/python3",
+    ]
+    .iter()
+    .map(|candidate| path.join(candidate))
+    .find(|candidate| candidate.is_file())
+}
+
+/// Locate and inspect the Python interpreter to use. Falls back to the one on PATH
+/// if nothing was requested.
+pub fn resolve(requested: Option<(String, InterpreterSource)>) -> Result<PythonInterpreter> {
+    let (executable, source) = match requested {
+        Some((path, source)) => {
+            let executable = find_executable(&path).ok_or_else(|| {
+                Error::Python(format!(
+                    "no Python interpreter found at {:?} (selected by {})",
+                    path, source
+                ))
+            })?;
+            (executable, source)
+        }
+        None => (
+            which::which("python")
+                .map_err(|_| Error::Python(String::from("Python was not found on PATH.")))?,
+            InterpreterSource::Path,
+        ),
+    };
+
+    let failed = |e: &dyn std::fmt::Display| {
+        Error::Python(format!(
+            "failed to run the Python interpreter at {:?}: {}",
+            executable, e
+        ))
+    };
+    let output = Command::new(&executable)
+        .args(["-c", QUERY])
+        .output()
+        .map_err(|e| failed(&e))?;
+    if !output.status.success() {
+        return Err(failed(&String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let info: InterpreterInfo = serde_json::from_slice(&output.stdout).map_err(|e| failed(&e))?;
+
+    // A virtualenv is not a full installation, it borrows everything but
+    // its packages from the base one.
+    let site_packages = if info.prefix != info.base_prefix {
+        info.site_packages
+    } else {
+        vec![]
+    };
+
+    Ok(PythonInterpreter {
+        executable,
+        source,
+        version: info.version,
+        home: info.base_prefix,
+        site_packages,
+    })
+}
@@ -0,0 +1,105 @@
+use pyo3::ffi::{
+    PyExc_KeyboardInterrupt, PyExc_TimeoutError, PyObject, PyThreadState_SetAsyncExc,
+    Py_IsInitialized,
+};
+use pyo3::prelude::*;
+use std::os::raw::c_long;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// The number of times Ctrl-C was pressed.
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// The identifier of the thread running Python code, 0 if there's none.
+static PYTHON_THREAD: AtomicI64 = AtomicI64::new(0);
+
+/// Install the Ctrl-C (and termination) handler.
+///
+/// The first interrupt lets the current evaluation finish and stops afterwards,
+/// so the result file is never left half-written. The second one cancels the
+/// running Python code by raising `KeyboardInterrupt` in it, and the third one
+/// exits immediately.
+pub fn install_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| match REQUESTS.fetch_add(1, Ordering::SeqCst) {
+        0 => eprintln!(
+            "\n[WARN] Interrupt received, stopping after the current file.
+       Press Ctrl-C again to cancel it."
+        ),
+        1 => {
+            eprintln!("\n[WARN] Cancelling the current evaluation..");
+            // Acquiring the GIL might block for a while, and we still want to
+            // be able to handle the next interrupt.
+            std::thread::spawn(|| raise_in_python(unsafe { PyExc_KeyboardInterrupt }));
+        }
+        _ => std::process::exit(130),
+    })
+}
+
+/// Whether a shutdown was requested.
+pub fn interrupted() -> bool {
+    REQUESTS.load(Ordering::SeqCst) > 0
+}
+
+/// Whether the running evaluation was requested to be cancelled.
+pub fn cancelled() -> bool {
+    REQUESTS.load(Ordering::SeqCst) > 1
+}
+
+/// Remember which thread runs Python, so we know where to raise `KeyboardInterrupt`.
+pub(crate) fn register_python_thread(ident: c_long) {
+    // `c_long` is only 32 bits wide on Windows
+    #[allow(clippy::useless_conversion)]
+    PYTHON_THREAD.store(i64::from(ident), Ordering::SeqCst);
+}
+
+/// Raise `exc` in the thread running Python, or clear the pending exception if it's null.
+fn raise_in_python(exc: *mut PyObject) {
+    let ident = PYTHON_THREAD.load(Ordering::SeqCst);
+    if ident == 0 || unsafe { Py_IsInitialized() } == 0 {
+        return;
+    }
+    Python::with_gil(|_py| unsafe {
+        // The exception is raised the next time that thread executes bytecode,
+        // then surfaces through `exec_py` as a regular Python error.
+        #[allow(clippy::cast_possible_truncation)]
+        PyThreadState_SetAsyncExc(ident as c_long, exc);
+    });
+}
+
+/// Aborts the running Python evaluation with `TimeoutError` if it takes too long.
+///
+/// Code that spends a long time inside a C extension is only aborted once it
+/// returns to the interpreter.
+#[derive(Debug)]
+pub struct Watchdog {
+    done: Sender<()>,
+    handle: JoinHandle<bool>,
+}
+
+impl Watchdog {
+    pub fn start(timeout: Duration) -> Self {
+        let (done, rx) = channel();
+        let handle = std::thread::spawn(move || match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                raise_in_python(unsafe { PyExc_TimeoutError });
+                true
+            }
+            _ => false,
+        });
+        Self { done, handle }
+    }
+
+    /// Stop the watchdog, and return whether it fired.
+    pub fn stop(self) -> bool {
+        let _ = self.done.send(());
+        let fired = self.handle.join().unwrap_or(false);
+        if fired {
+            // The evaluation might have finished right before the exception was
+            // set, don't let it leak into the next one.
+            raise_in_python(std::ptr::null_mut());
+        }
+        fired
+    }
+}
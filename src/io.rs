@@ -1,7 +1,9 @@
 use crate::deserialize::LoadOptions;
+use crate::error::{Error, Result};
 use crate::utils::get_exclude_patterns;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 use std::{ffi::OsStr, fs, io, path::PathBuf};
 
 pub fn create_results_file(filename: &str) -> std::io::Result<()> {
@@ -10,13 +12,109 @@ pub fn create_results_file(filename: &str) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Write every entry of the result file. They go to a temporary file first, which then
+/// replaces the result file, so an interrupted write can't leave it truncated.
+pub fn write_results(
+    filename: &Path,
+    results: &serde_json::Map<String, serde_json::Value>,
+) -> io::Result<()> {
+    let dir = filename
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut file = tempfile::Builder::new()
+        .prefix(".psc_results")
+        .suffix(".json")
+        .tempfile_in(dir)?;
+    serde_json::to_writer_pretty(&mut file, results)?;
+    file.persist(filename)?;
+    Ok(())
+}
+
+/// The number of output lines to keep in the error reports.
+pub const OUTPUT_TAIL_LINES: usize = 10;
+
+/// Save what the evaluation of `filename` printed to `<root>/logs/<filename>.log`.
+pub fn write_log(root: &str, filename: &str, content: &str) -> io::Result<PathBuf> {
+    let logs = PathBuf::from(root).join("logs");
+    fs::create_dir_all(&logs)?;
+    let path = logs.join(format!("{}.log", filename));
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// The last `n` lines of `content`.
+pub fn last_lines(content: &str, n: usize) -> String {
+    let lines = content.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+/// Read the names of the files to evaluate from `list`, one per line. Empty lines and
+/// lines starting with `#` are ignored. The files are expected to be in `root`.
+pub fn read_file_list(list: &str, root: &str) -> Result<Vec<PathBuf>> {
+    let content = fs::read_to_string(list)
+        .map_err(|e| Error::Config(format!("failed to read {:?}: {}", list, e)))?;
+    let mut files = vec![];
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let file = Path::new(root).join(line);
+        if !file.is_file() || file.parent() != Some(Path::new(root)) {
+            return Err(Error::Config(format!(
+                "{:?} listed in {:?} is not a file in {:?}",
+                line, list, root
+            )));
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Why a file with a matching extension was not selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exclusion {
+    /// It's listed in `skip_files`.
+    SkipFile,
+    /// It matches this pattern of `exclude_patterns`.
+    Pattern(String),
+}
+
+impl std::fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SkipFile => write!(f, "listed in skip_files"),
+            Self::Pattern(pattern) => write!(f, "matches exclude pattern {:?}", pattern),
+        }
+    }
+}
+
+/// The files to evaluate, and the ones left out on purpose.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSelection {
+    pub selected: Vec<PathBuf>,
+    pub excluded: Vec<(PathBuf, Exclusion)>,
+}
+
 pub fn get_files(root: &str, file_pattern_options: &LoadOptions) -> io::Result<Vec<PathBuf>> {
-    let mut result = vec![];
+    Ok(select_files(root, file_pattern_options)?.selected)
+}
+
+/// Select the files of `root` to evaluate, and remember why the others with
+/// a matching extension were left out.
+pub fn select_files(root: &str, file_pattern_options: &LoadOptions) -> io::Result<FileSelection> {
+    let mut selection = FileSelection::default();
 
     let ext_as_str_ref = file_pattern_options.extensions.clone().into_comparable();
 
     let skips_as_str_ref = file_pattern_options.skip_files.clone().into_comparable();
 
+    let exclude_patterns = file_pattern_options
+        .exclude_patterns
+        .clone()
+        .into_comparable();
+    let exclude_matchers = get_exclude_patterns(file_pattern_options);
+
     for path in fs::read_dir(root)? {
         let path = path?.path();
 
@@ -24,38 +122,106 @@ pub fn get_files(root: &str, file_pattern_options: &LoadOptions) -> io::Result<V
         if path.is_dir() {
             continue;
         }
-        // early bailout of skip files
-        if skips_as_str_ref.contains(
+        // pick up files that have the specified extensions
+        if !ext_as_str_ref.contains(
             &path
-                .file_name()
+                .extension()
                 .and_then(OsStr::to_str)
-                .unwrap_or("__nofilename")
+                .unwrap_or("__noextension")
                 .to_owned(),
         ) {
             continue;
         }
-        // pick up files that have the specified extensions
-        if ext_as_str_ref.contains(
+        if skips_as_str_ref.contains(
             &path
-                .extension()
+                .file_name()
                 .and_then(OsStr::to_str)
-                .unwrap_or("__noextension")
+                .unwrap_or("__nofilename")
                 .to_owned(),
         ) {
-            result.push(path.to_owned());
+            selection.excluded.push((path, Exclusion::SkipFile));
+            continue;
+        }
+        // exclude every file that matches any pre-defined pattern
+        let matched = exclude_matchers
+            .iter()
+            .position(|pattern| pattern.matches(path.to_str().unwrap_or_default()));
+        if let Some(i) = matched {
+            selection
+                .excluded
+                .push((path, Exclusion::Pattern(exclude_patterns[i].clone())));
+        } else {
+            selection.selected.push(path);
         }
     }
 
-    let exclude_patterns = get_exclude_patterns(file_pattern_options);
+    selection.selected.sort();
+    selection.excluded.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(selection)
+}
 
-    // exclude every file that matches any pre-defined pattern
-    result.retain(|path| {
-        !exclude_patterns
-            .iter()
-            .map(|pattern| pattern.matches(path.to_str().unwrap()))
-            .any(|op| op)
-    });
+#[test]
+fn last_lines_keeps_the_tail() {
+    assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc");
+    assert_eq!(last_lines("a", 5), "a");
+    assert_eq!(last_lines("", 5), "");
+}
+
+#[test]
+fn excluded_files_keep_their_reason() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.trt", "b_3.trt", "skip.trt", "notes.txt"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let options: LoadOptions = serde_yaml::from_str(
+        "extensions: trt\nexclude_patterns: \"*_3.trt\"\nskip_files: skip.trt\nskiprows: 0\nmeta_len: 0\ndecimal: \".\"\ndelimiter: \",\"\n",
+    )
+    .unwrap();
+
+    let selection = select_files(dir.path().to_str().unwrap(), &options).unwrap();
+    assert_eq!(selection.selected, vec![dir.path().join("a.trt")]);
+    assert_eq!(
+        selection.excluded,
+        vec![
+            (
+                dir.path().join("b_3.trt"),
+                Exclusion::Pattern(String::from("*_3.trt"))
+            ),
+            (dir.path().join("skip.trt"), Exclusion::SkipFile),
+        ]
+    );
+}
+
+#[test]
+fn file_lists_skip_comments_and_stay_in_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    fs::write(dir.path().join("a.trt"), "").unwrap();
+    let list = dir.path().join("list.txt");
+
+    fs::write(&list, "# first run\na.trt\n\n").unwrap();
+    assert_eq!(
+        read_file_list(list.to_str().unwrap(), root).unwrap(),
+        vec![dir.path().join("a.trt")]
+    );
+
+    fs::write(&list, "../a.trt\n").unwrap();
+    assert!(read_file_list(list.to_str().unwrap(), root).is_err());
+}
+
+#[test]
+fn results_replace_the_file_at_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("results.json");
+    fs::write(&file, "{ \"old\": 1 }").unwrap();
+
+    let mut results = serde_json::Map::new();
+    results.insert(String::from("a.trt"), serde_json::json!({ "GD": 1.0 }));
+    write_results(&file, &results).unwrap();
 
-    result.sort();
-    Ok(result)
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(written, serde_json::Value::Object(results));
+    // the temporary file is renamed, nothing is left behind
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
 }
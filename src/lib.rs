@@ -19,11 +19,23 @@
 
 pub mod app;
 pub mod audit;
+pub mod backend;
 pub mod codegen;
+pub mod dashboard;
 pub mod deserialize;
+pub mod doctor;
+pub mod error;
+pub mod evaluator;
+pub mod interpreter;
+pub mod interrupt;
 pub mod io;
 pub mod parser;
 pub mod python;
+pub mod report;
+pub mod run;
 pub mod statistics;
+pub mod sweep;
 pub mod utils;
 pub mod watch;
+
+pub use error::{Error, Result};
@@ -1,5 +1,5 @@
 use pysprint_cli::app;
 
 fn main() {
-    app::launch();
+    std::process::exit(app::launch());
 }
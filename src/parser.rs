@@ -1,7 +1,819 @@
-use crate::deserialize::Config;
+use crate::deserialize::{migrate, Config, CONFIG_VERSION};
+use crate::error::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-pub fn parse(file: &str) -> Result<Config, Box<dyn std::error::Error>> {
-    let r = std::fs::File::open(file)?;
-    let config: Config = serde_yaml::from_reader(r)?;
+pub fn parse(file: &str) -> Result<Config> {
+    let raw = resolve(file)?;
+    let config = deserialize(raw)
+        .and_then(|config| config.validate().map(|()| config))
+        .map_err(|e| Error::Config(format!("failed to parse {:?}: {}", file, e)))?;
     Ok(config)
 }
+
+/// Deserialize the resolved config, the `method_details` can be left out if the method's
+/// defaults are fine.
+fn deserialize(mut raw: Value) -> std::result::Result<Config, String> {
+    if let Value::Mapping(map) = &mut raw {
+        let key = Value::String(String::from("method_details"));
+        match map.get_mut(&key) {
+            Some(details) if details.is_null() => *details = Value::Mapping(Mapping::new()),
+            Some(_) => {}
+            None => {
+                map.insert(key, Value::Mapping(Mapping::new()));
+            }
+        }
+    }
+    serde_yaml::from_value(raw).map_err(|e| e.to_string())
+}
+
+/// Read the config file, merged onto the file it `extends`, if there's one.
+pub fn resolve(file: &str) -> Result<Value> {
+    resolve_chain(Path::new(file), &mut vec![])
+}
+
+/// The names of the config files that are looked for, in the order of preference.
+pub const CONFIG_NAMES: [&str; 3] = ["eval.yaml", "eval.toml", "eval.json"];
+
+/// The formats a config file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// The format of the file by its extension, anything unknown is read as YAML.
+    pub fn of(file: &Path) -> Self {
+        match file.extension().and_then(OsStr::to_str) {
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            _ => Self::Yaml,
+        }
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    fn read(self, content: &str) -> std::result::Result<Value, String> {
+        match self {
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            Self::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Write the raw config in this format. TOML has no null, so the keys set to null are
+    /// left out of it.
+    pub fn write(self, raw: &Value) -> std::result::Result<String, String> {
+        match self {
+            Self::Yaml => serde_yaml::to_string(raw).map_err(|e| e.to_string()),
+            Self::Toml => toml::Value::try_from(without_nulls(raw.clone()))
+                .and_then(|value| toml::to_string_pretty(&value))
+                .map_err(|e| e.to_string()),
+            Self::Json => serde_json::to_string_pretty(raw)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("expected yaml, toml or json, found {}", s)),
+        }
+    }
+}
+
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+/// Read the config file as it's written, in the format of its extension.
+fn read_raw(file: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| Error::Config(format!("failed to open {:?}: {}", file, e)))?;
+    ConfigFormat::of(file)
+        .read(&content)
+        .map_err(|e| Error::Config(format!("failed to parse {:?}: {}", file, e)))
+}
+
+/// Read the config file as it's written, upgraded to the current layout. Returns the
+/// changes the upgrade made to it.
+pub fn read_migrated(file: &Path) -> Result<(Value, Vec<String>)> {
+    let mut raw = read_raw(file)?;
+    let changes = migrate(&mut raw).map_err(|e| Error::Config(format!("{} in {:?}", e, file)))?;
+    Ok((raw, changes))
+}
+
+/// Rewrite the config file in the current layout, after copying it to a backup. Returns the
+/// backup and the changes made, or `None` if the file is up to date.
+pub fn migrate_file(file: &Path) -> Result<Option<(PathBuf, Vec<String>)>> {
+    let (migrated, changes) = read_migrated(file)?;
+    if changes.is_empty() && written_version(file)? == Some(CONFIG_VERSION) {
+        return Ok(None);
+    }
+    let mut backup = PathBuf::from(format!("{}.bak", file.display()));
+    let mut n = 1;
+    while backup.exists() {
+        backup = PathBuf::from(format!("{}.bak.{}", file.display(), n));
+        n += 1;
+    }
+    std::fs::copy(file, &backup)?;
+    let content = ConfigFormat::of(file)
+        .write(&migrated)
+        .map_err(|e| Error::Config(format!("failed to serialize the config: {}", e)))?;
+    std::fs::write(file, content)?;
+    Ok(Some((backup, changes)))
+}
+
+/// The version written in the config file, if there's one.
+fn written_version(file: &Path) -> Result<Option<u64>> {
+    Ok(read_raw(file)?.get("version").and_then(Value::as_u64))
+}
+
+/// Write the config file into `output` in `format`, as it's written: the extended file,
+/// the environment variables and the older layouts are left for the one reading it.
+pub fn convert_file(file: &Path, format: ConfigFormat, output: &Path) -> Result<()> {
+    let raw = read_raw(file)?;
+    let content = format
+        .write(&raw)
+        .map_err(|e| Error::Config(format!("failed to convert {:?}: {}", file, e)))?;
+    std::fs::write(output, content)
+        .map_err(|e| Error::Config(format!("failed to write {:?}: {}", output, e)))
+}
+
+fn resolve_chain(file: &Path, chain: &mut Vec<PathBuf>) -> Result<Value> {
+    let (raw, _) = read_migrated(file)?;
+    let mut raw = interpolate(raw, "", &env_var)
+        .map_err(|e| Error::Config(format!("{} in {:?}", e, file)))?;
+
+    let canonical = file.canonicalize()?;
+    if chain.contains(&canonical) {
+        return Err(Error::Config(format!(
+            "{:?} extends itself through {:?}",
+            file,
+            chain.last().unwrap_or(&canonical)
+        )));
+    }
+    chain.push(canonical);
+
+    let base = match &mut raw {
+        Value::Mapping(map) => map.remove(&Value::String(String::from("extends"))),
+        _ => None,
+    };
+    let base = match base {
+        Some(Value::String(base)) => {
+            // relative to the file extending it, not the working directory
+            let base = file.parent().unwrap_or_else(|| Path::new(".")).join(base);
+            resolve_chain(&base, chain)?
+        }
+        Some(other) => {
+            return Err(Error::Config(format!(
+                "expected a path in the extends key of {:?}, found {:?}",
+                file, other
+            )))
+        }
+        None => Value::Null,
+    };
+    merge_config(base, raw)
+}
+
+/// Merge the config `overlay` onto `base`. The `method_details` of `base` are dropped if
+/// `overlay` changes the method, they belong to the method they're written for.
+fn merge_config(mut base: Value, overlay: Value) -> Result<Value> {
+    if let Some(method) = overlay.get("method") {
+        if base.get("method").is_some_and(|base| base != method) {
+            if let Value::Mapping(map) = &mut base {
+                map.remove(&Value::String(String::from("method_details")));
+            }
+        }
+    }
+    merge(base, overlay)
+}
+
+/// Merge `overlay` onto `base`. Sections are merged key by key, anything else in `overlay`
+/// replaces the value in `base`, except the lists under a `key+` which are appended to it.
+fn merge(base: Value, overlay: Value) -> Result<Value> {
+    let Value::Mapping(overlay) = overlay else {
+        return Ok(overlay);
+    };
+    let mut merged = match base {
+        Value::Mapping(base) => base,
+        _ => Mapping::new(),
+    };
+    for (key, value) in overlay {
+        // the keys stay in the order of the base, and the new ones go to the end
+        if let Some(name) = key.as_str().and_then(|key| key.strip_suffix('+')) {
+            let name = Value::String(name.to_owned());
+            let Value::Sequence(appended) = value else {
+                return Err(Error::Config(format!(
+                    "expected a list to append to {}, found {:?}",
+                    name.as_str().unwrap_or_default(),
+                    value
+                )));
+            };
+            if let Some(existing) = merged.get_mut(&name) {
+                let mut items = match std::mem::take(existing) {
+                    Value::Sequence(items) => items,
+                    Value::Null => vec![],
+                    item => vec![item],
+                };
+                items.extend(appended);
+                *existing = Value::Sequence(items);
+            } else {
+                merged.insert(name, Value::Sequence(appended));
+            }
+        } else if let Some(existing) = merged.get_mut(&key) {
+            *existing = merge(std::mem::take(existing), value)?;
+        } else {
+            let value = merge(Value::Null, value)?;
+            merged.insert(key, value);
+        }
+    }
+    Ok(Value::Mapping(merged))
+}
+
+/// The value of an environment variable, for [`interpolate`].
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// The keys holding Python code, which is left as it's written.
+const CODE_KEYS: [&str; 2] = ["before_evaluate", "after_evaluate"];
+
+/// Expand the variables looked up by `var` and the `~` in every string of the config,
+/// except in the Python code. `key` is the dotted path of `value`, it's only used in the
+/// error messages.
+fn interpolate<F>(value: Value, key: &str, var: &F) -> std::result::Result<Value, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match value {
+        Value::String(s) => expand(&s, var).map(Value::String).map_err(|e| {
+            if key.is_empty() {
+                e
+            } else {
+                format!("{} at {}", e, key)
+            }
+        }),
+        Value::Sequence(items) => items
+            .into_iter()
+            .map(|item| interpolate(item, key, var))
+            .collect::<std::result::Result<_, _>>()
+            .map(Value::Sequence),
+        Value::Mapping(map) => map
+            .into_iter()
+            .map(|(k, v)| {
+                let path = match (key, k.as_str()) {
+                    ("", Some(name)) => name.to_owned(),
+                    (_, Some(name)) => format!("{}.{}", key, name),
+                    _ => key.to_owned(),
+                };
+                if k.as_str().is_some_and(|name| CODE_KEYS.contains(&name)) {
+                    return Ok((k, v));
+                }
+                Ok((k, interpolate(v, &path, var)?))
+            })
+            .collect::<std::result::Result<_, String>>()
+            .map(Value::Mapping),
+        other => Ok(other),
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` to the value of the variable looked up by `var`,
+/// and a leading `~` to the home directory. The default is used when the variable is unset
+/// or empty, and `$${` is kept as a literal `${`.
+fn expand<F>(s: &str, var: &F) -> std::result::Result<String, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let expanded = match s.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            let home = home_dir()
+                .ok_or_else(|| String::from("can't expand ~, the home directory is not known"))?;
+            format!("{}{}", home.display(), rest)
+        }
+        _ => s.to_owned(),
+    };
+
+    let mut result = String::with_capacity(expanded.len());
+    let mut rest = expanded.as_str();
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed ${{ in {:?}", s))?;
+        let inner = &rest[start + 2..start + end];
+        let (name, default) = match inner.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid variable name {:?} in {:?}", name, s));
+        }
+        match (var(name).filter(|v| !v.is_empty()), default) {
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => {
+                return Err(format!(
+                "the environment variable {} is not set (use ${{{}:-default}} to give a default)",
+                name, name
+            ))
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// The home directory of the user, from `HOME`, or `USERPROFILE` on Windows.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// A `section.key=value` override of a config value, given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    pub key: String,
+    pub value: Value,
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected section.key=value, found {}", s))?;
+        let key = key.trim();
+        if key.is_empty() || key.split('.').any(str::is_empty) {
+            return Err(format!("expected section.key=value, found {}", s));
+        }
+        // numbers and booleans are typed, anything else is taken as a string
+        let value = serde_yaml::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+        Ok(Self {
+            key: key.to_owned(),
+            value,
+        })
+    }
+}
+
+impl Override {
+    /// Set the value in the raw config, creating the missing sections. Changing the method
+    /// drops the `method_details` of the previous one.
+    fn apply(&self, raw: &mut Value) -> Result<()> {
+        if self.key == "method" && raw.get("method") != Some(&self.value) {
+            if let Value::Mapping(map) = raw {
+                map.remove(&Value::String(String::from("method_details")));
+            }
+        }
+        let path = self.key.split('.').collect::<Vec<_>>();
+        self.set(raw, &path)
+    }
+
+    fn set(&self, node: &mut Value, path: &[&str]) -> Result<()> {
+        let Some((section, rest)) = path.split_first() else {
+            *node = self.value.clone();
+            return Ok(());
+        };
+        if node.is_null() {
+            *node = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(map) = node else {
+            return Err(Error::Config(format!(
+                "can't set {}, its parent is not a section",
+                self.key
+            )));
+        };
+        let section = Value::String((*section).to_owned());
+        if let Some(child) = map.get_mut(&section) {
+            return self.set(child, rest);
+        }
+        let mut child = Value::Null;
+        self.set(&mut child, rest)?;
+        map.insert(section, child);
+        Ok(())
+    }
+}
+
+/// The value at the dotted `key` of `value`, if there's one.
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |node, section| node.get(section))
+}
+
+/// The effective value of every overridden key.
+pub type Overridden = Vec<(String, Value)>;
+
+/// Parse the config file with the `profile` overlaid on it, then apply `overrides` in order.
+///
+/// The values are type checked against the fields they override. Returns the config, and
+/// the effective value of every overridden key.
+pub fn parse_with_overrides(
+    file: &str,
+    profile: Option<&str>,
+    overrides: &[Override],
+) -> Result<(Config, Overridden)> {
+    let (_, config, effective) = resolve_with_overrides(file, profile, overrides)?;
+    Ok((config, effective))
+}
+
+/// Like [`parse_with_overrides`], but the resolved config file is returned too.
+pub fn resolve_with_overrides(
+    file: &str,
+    profile: Option<&str>,
+    overrides: &[Override],
+) -> Result<(Value, Config, Overridden)> {
+    let mut raw = apply_profile(resolve(file)?, profile)
+        .map_err(|e| Error::Config(format!("{} in {:?}", e, file)))?;
+    let mut config = deserialize(raw.clone())
+        .map_err(|e| Error::Config(format!("failed to parse {:?}: {}", file, e)))?;
+
+    for o in overrides {
+        o.apply(&mut raw)?;
+        config = deserialize(raw.clone())
+            .map_err(|e| Error::Config(format!("invalid value for {}: {}", o.key, e)))?;
+        // unknown keys are ignored by the deserializer, so they don't show up here
+        let parsed = serde_yaml::to_value(&config)
+            .map_err(|e| Error::Config(format!("invalid value for {}: {}", o.key, e)))?;
+        if lookup(&parsed, &o.key).is_none() {
+            return Err(Error::Config(format!("unknown config key {}", o.key)));
+        }
+    }
+
+    // the options are only checked together, an override might resolve a conflict
+    config
+        .validate()
+        .map_err(|e| Error::Config(format!("failed to parse {:?}: {}", file, e)))?;
+    let parsed = serde_yaml::to_value(&config).unwrap_or_default();
+    let effective = overrides
+        .iter()
+        .map(|o| {
+            let value = lookup(&parsed, &o.key).cloned().unwrap_or_default();
+            (o.key.clone(), value)
+        })
+        .collect();
+    config.profile = profile.map(String::from);
+    Ok((raw, config, effective))
+}
+
+/// Overlay the profile named `name` on the config, the same way a file is merged onto the
+/// one it extends. The profiles are left out of the result either way.
+fn apply_profile(mut raw: Value, name: Option<&str>) -> std::result::Result<Value, String> {
+    let profiles = match &mut raw {
+        Value::Mapping(map) => map.remove(&Value::String(String::from("profiles"))),
+        _ => None,
+    };
+    let Some(name) = name else {
+        return Ok(raw);
+    };
+    let Some(Value::Mapping(profiles)) = profiles else {
+        return Err(format!(
+            "there's no profile named {}, no profiles are defined",
+            name
+        ));
+    };
+    let Some(profile) = profiles.get(&Value::String(name.to_owned())) else {
+        let names = profiles
+            .iter()
+            .filter_map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        return Err(format!(
+            "there's no profile named {}, the defined ones are {}",
+            name,
+            names.join(", ")
+        ));
+    };
+    merge_config(raw, profile.clone()).map_err(|e| e.to_string())
+}
+
+/// The first of the config files named `names` that's in `dir`.
+pub fn config_in(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    names
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Find one of the config files named `names` in `dir`, or in the closest of its parents.
+/// Like git, the search stops at the root of the repository `dir` is in.
+pub fn find_config(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        if let Some(config) = config_in(dir, names) {
+            return Some(config);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// The directory of the user's default config, `$XDG_CONFIG_HOME/psc` or `~/.config/psc`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("psc"))
+}
+
+/// Find the config file of `dir` the way [`find_config`] does, falling back to the user's
+/// default config if there's none.
+pub fn discover_config(dir: &Path) -> Option<PathBuf> {
+    discover_config_with(dir, user_config_dir().as_deref())
+}
+
+/// [`discover_config`] with `user_dir` as the directory of the user's default config.
+fn discover_config_with(dir: &Path, user_dir: Option<&Path>) -> Option<PathBuf> {
+    // the parents of a relative path are only known after resolving it
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    find_config(&dir, &CONFIG_NAMES)
+        .or_else(|| user_dir.and_then(|dir| config_in(dir, &CONFIG_NAMES)))
+}
+
+#[test]
+fn config_is_found_in_parents() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("a").join("b");
+    std::fs::create_dir_all(&nested).unwrap();
+    assert_eq!(find_config(&nested, &CONFIG_NAMES), None);
+
+    std::fs::write(dir.path().join("eval.json"), "").unwrap();
+    assert_eq!(
+        find_config(&nested, &CONFIG_NAMES),
+        Some(dir.path().join("eval.json"))
+    );
+    std::fs::write(dir.path().join("eval.yaml"), "").unwrap();
+    assert_eq!(
+        find_config(&nested, &CONFIG_NAMES),
+        Some(dir.path().join("eval.yaml"))
+    );
+
+    // the root of a repository is as far as it goes
+    std::fs::create_dir(dir.path().join("a").join(".git")).unwrap();
+    assert_eq!(find_config(&nested, &CONFIG_NAMES), None);
+    std::fs::write(dir.path().join("a").join("eval.toml"), "").unwrap();
+    assert_eq!(
+        find_config(&nested, &CONFIG_NAMES),
+        Some(dir.path().join("a").join("eval.toml"))
+    );
+}
+
+#[test]
+fn user_config_is_the_fallback() {
+    let user = tempfile::tempdir().unwrap();
+    let user_dir = user.path().join("psc");
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(".git")).unwrap();
+    assert_eq!(discover_config_with(dir.path(), Some(&user_dir)), None);
+
+    let default = user_dir.join("eval.json");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::write(&default, "{}").unwrap();
+    assert_eq!(
+        discover_config_with(dir.path(), Some(&user_dir)),
+        Some(default)
+    );
+
+    std::fs::write(dir.path().join("eval.yaml"), "").unwrap();
+    assert_eq!(
+        discover_config_with(dir.path(), Some(&user_dir)),
+        Some(dir.path().canonicalize().unwrap().join("eval.yaml"))
+    );
+}
+
+#[test]
+fn formats_are_converted_losslessly() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "wft").unwrap();
+    let yaml = dir.path().join("eval.yaml");
+    let config = parse(&yaml.to_string_lossy()).unwrap();
+
+    for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+        let converted = dir.path().join(format!("eval.{}", format.extension()));
+        convert_file(&yaml, format, &converted).unwrap();
+        assert_eq!(ConfigFormat::of(&converted), format);
+        assert_eq!(parse(&converted.to_string_lossy()).unwrap(), config);
+
+        let back = dir.path().join(format!("back.{}.yaml", format.extension()));
+        convert_file(&converted, ConfigFormat::Yaml, &back).unwrap();
+        assert_eq!(parse(&back.to_string_lossy()).unwrap(), config);
+    }
+    assert!("ini".parse::<ConfigFormat>().is_err());
+}
+
+#[test]
+fn overrides_are_type_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "wft").unwrap();
+    let file = format!("{}/eval.yaml", root);
+    let set = |overrides: &[&str]| {
+        let overrides = overrides
+            .iter()
+            .map(|o| o.parse::<Override>().unwrap())
+            .collect::<Vec<_>>();
+        parse_with_overrides(&file, None, &overrides)
+    };
+
+    let (config, effective) = set(&["method_details.windows=300", "evaluate.order=4"]).unwrap();
+    assert_eq!(config.method.name(), "wft");
+    assert_eq!(
+        effective,
+        vec![
+            (String::from("method_details.windows"), Value::from(300)),
+            (String::from("evaluate.order"), Value::from(4)),
+        ]
+    );
+    assert!(set(&["method_details.windows=many"]).is_err());
+    assert!(set(&["method_details.window=300"]).is_err());
+
+    // the options of the previous method are dropped with it
+    let (config, _) = set(&["method_details.windows=300", "method=fft"]).unwrap();
+    assert_eq!(config.method.name(), "fft");
+    assert!(set(&["method=fft", "method_details.windows=300"]).is_err());
+
+    // conflicting options are rejected, unless a later override resolves them
+    assert!(set(&["method_details.std=0.1"]).is_err());
+    assert!(set(&["method_details.std=0.1", "method_details.fwhm=null"]).is_ok());
+    assert!(set(&["method_details.windows=0"]).is_err());
+    assert!("method_details.windows".parse::<Override>().is_err());
+}
+
+#[test]
+fn extended_configs_are_merged() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "fft").unwrap();
+    let sample = dir.path().join("sample");
+    std::fs::create_dir(&sample).unwrap();
+    std::fs::write(
+        sample.join("eval.yaml"),
+        "
+extends: ../eval.yaml
+load_options:
+  extensions+: [csv]
+  skip_files: [calibration.trt]
+preprocess:
+  slice_start: 1.5
+",
+    )
+    .unwrap();
+
+    let raw = resolve(&sample.join("eval.yaml").to_string_lossy()).unwrap();
+    let load_options = &raw["load_options"];
+    assert_eq!(
+        load_options["extensions"],
+        serde_yaml::from_str::<Value>("[trt, txt, csv]").unwrap()
+    );
+    assert_eq!(
+        load_options["skip_files"],
+        serde_yaml::from_str::<Value>("[calibration.trt]").unwrap()
+    );
+    // the rest of the section is kept from the base
+    assert_eq!(load_options["skiprows"], Value::from(8));
+    assert_eq!(raw["preprocess"]["slice_start"], Value::from(1.5));
+    assert_eq!(raw["preprocess"]["input_unit"], Value::from("nm"));
+    assert!(raw.get("extends").is_none());
+    assert!(parse(&sample.join("eval.yaml").to_string_lossy()).is_ok());
+
+    std::fs::write(dir.path().join("eval.yaml"), "extends: sample/eval.yaml\n").unwrap();
+    assert!(resolve(&sample.join("eval.yaml").to_string_lossy()).is_err());
+}
+
+#[test]
+fn profiles_overlay_the_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "wft").unwrap();
+    let file = format!("{}/eval.yaml", root);
+    let mut content = std::fs::read_to_string(&file).unwrap();
+    content.push_str(
+        "
+profiles:
+  full:
+    method_details:
+      heatmap: true
+  fourier:
+    method: fft
+",
+    );
+    std::fs::write(&file, content).unwrap();
+
+    let (raw, config, _) = resolve_with_overrides(&file, Some("full"), &[]).unwrap();
+    assert_eq!(config.method.name(), "wft");
+    assert_eq!(config.profile.as_deref(), Some("full"));
+    assert_eq!(raw["method_details"]["heatmap"], Value::from(true));
+    // the rest of the section is kept
+    assert_eq!(raw["method_details"]["windows"], Value::from(200));
+    assert!(raw.get("profiles").is_none());
+
+    // switching the method starts its options afresh
+    let (raw, config, _) = resolve_with_overrides(&file, Some("fourier"), &[]).unwrap();
+    assert_eq!(config.method.name(), "fft");
+    assert!(raw.get("method_details").is_none());
+
+    let (config, _) = parse_with_overrides(&file, None, &[]).unwrap();
+    assert_eq!(config.method.name(), "wft");
+    assert_eq!(config.profile, None);
+    assert!(parse_with_overrides(&file, Some("quick"), &[]).is_err());
+}
+
+#[test]
+fn variables_are_expanded() {
+    let var = |name: &str| (name == "DATA").then(|| String::from("/data"));
+    let expand = |s: &str| expand(s, &var);
+    assert_eq!(expand("${DATA}/skip.trt").unwrap(), "/data/skip.trt");
+    assert_eq!(expand("${UNSET:-/tmp}/${DATA:-x}").unwrap(), "/tmp//data");
+    assert_eq!(expand("cost: $${DATA}").unwrap(), "cost: ${DATA}");
+    assert!(expand("${UNSET}").unwrap_err().contains("UNSET is not set"));
+    assert!(expand("${DATA").is_err());
+    // only a leading ~ is a home directory
+    assert!(!expand("~/results").unwrap().starts_with('~'));
+    assert_eq!(expand("a ~/b").unwrap(), "a ~/b");
+    assert_eq!(expand("~x").unwrap(), "~x");
+
+    let raw = serde_yaml::from_str("load_options:\n  skip_files: [\"${UNSET}\"]").unwrap();
+    assert!(interpolate(raw, "", &var)
+        .unwrap_err()
+        .ends_with("at load_options.skip_files"));
+
+    // the Python code is left alone, in the profiles too
+    let code = "before_evaluate: [\"print(f'${x}')\"]";
+    let raw: Value =
+        serde_yaml::from_str(&format!("{}\nprofiles:\n  a:\n    {}", code, code)).unwrap();
+    assert_eq!(interpolate(raw.clone(), "", &var).unwrap(), raw);
+}
+
+#[test]
+fn old_layouts_are_migrated() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    crate::codegen::write_default_yaml_with_method(root, "spp").unwrap();
+    let file = dir.path().join("eval.yaml");
+    assert!(migrate_file(&file).unwrap().is_none());
+
+    let current = parse(&file.to_string_lossy()).unwrap();
+    let old = std::fs::read_to_string(&file)
+        .unwrap()
+        .replace("version: 2\n", "")
+        .replace(
+            "method_details:\n",
+            "method_details:\n  windows: 200\n  only_phase: false\n",
+        );
+    std::fs::write(&file, &old).unwrap();
+    // read the same way before and after the migration
+    assert_eq!(parse(&file.to_string_lossy()).unwrap(), current);
+
+    let (backup, changes) = migrate_file(&file).unwrap().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), old);
+    assert_eq!(parse(&file.to_string_lossy()).unwrap(), current);
+    assert!(migrate_file(&file).unwrap().is_none());
+
+    // a typo is not taken for the detail of another method
+    std::fs::write(&file, old.replace("  windows: 200\n", "  windws: 200\n")).unwrap();
+    assert!(parse(&file.to_string_lossy())
+        .unwrap_err()
+        .to_string()
+        .contains("windws"));
+
+    std::fs::write(&file, "version: 99\n").unwrap();
+    assert!(parse(&file.to_string_lossy()).is_err());
+}
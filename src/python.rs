@@ -1,81 +1,174 @@
+use crate::backend::{ExceptionInfo, Execution, Handshake};
+use crate::codegen::{select_templates_for, Templates};
+use crate::error::{Error, Result};
+use crate::interpreter::{embedded_version, PythonInterpreter};
+use crate::interrupt::register_python_thread;
 use crate::utils::get_spinner;
+use pyo3::exceptions::{PyNameError, PyValueError};
 use pyo3::ffi::Py_SetPythonHome;
 use pyo3::prelude::*;
-use pyo3::types::IntoPyDict;
-use std::io::Write;
-use std::path::PathBuf;
-use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use pyo3::types::{IntoPyDict, PyDict};
+use std::os::raw::c_long;
 
 /// Set the necessary environment variables for the Python interpreter
 /// and initialize it.
 /// Also takes account for Anaconda distribution.
-pub fn prepare_python() -> Result<(), pyo3::PyErr> {
+pub fn prepare_python(interpreter: Option<&PythonInterpreter>) -> PyResult<()> {
     // If we're already initialized, just return.
     unsafe {
-        if pyo3::ffi::Py_IsInitialized() == 0 {
+        if pyo3::ffi::Py_IsInitialized() != 0 {
             return Ok(());
         }
     }
 
     // Due to https://github.com/ContinuumIO/anaconda-issues/issues/11439,
-    // we first need to set PYTHONHOME. To do so, we will look for whatever
-    // directory on PATH currently has python.exe.
-
-    let python_exe = which::which("python").expect("Python was not found on PATH.");
-    let python_home = python_exe.parent().unwrap();
-
-    // The Python C API uses null-terminated UTF-16 strings, so we need to
-    // encode the path into that format here.
-    // We could use the Windows FFI modules provided in the standard library,
-    // but we want this to work cross-platform, so we do things more manually.
-    let mut python_home = python_home
-        .to_str()
-        .unwrap()
-        .encode_utf16()
-        .collect::<Vec<u16>>();
-    // Add a null terminator.
-    python_home.push(0);
-
-    cfg_if::cfg_if! {
-        if #[cfg(target_os = "windows")] {
-        unsafe {
-                Py_SetPythonHome(python_home.as_ptr());
-            }
-        } else {
-            unsafe {
-                Py_SetPythonHome(python_home.as_ptr() as *const i32);
+    // we first need to set PYTHONHOME to the root of the selected installation.
+    // Pointing it to a different Python version than the one we're linked
+    // against would crash the interpreter, so we only do that if they match.
+    if let Some(interpreter) = interpreter.filter(|i| i.matches_embedded()) {
+        let python_home = interpreter.home.to_str().unwrap();
+
+        // The Python C API uses null-terminated wide strings, so we need to
+        // encode the path into that format here. That's UTF-16 on Windows
+        // and UTF-32 everywhere else.
+        // We could use the Windows FFI modules provided in the standard library,
+        // but we want this to work cross-platform, so we do things more manually.
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "windows")] {
+                let mut python_home = python_home.encode_utf16().collect::<Vec<u16>>();
+            } else {
+                let mut python_home = python_home.chars().map(|c| c as i32).collect::<Vec<i32>>();
             }
         }
+        // Add a null terminator.
+        python_home.push(0);
+
+        // Python keeps the pointer, so the buffer must live as long as we do.
+        let python_home = python_home.leak();
+        unsafe {
+            Py_SetPythonHome(python_home.as_ptr());
+        }
     }
 
     // Once we've set the configuration we need, we can go on and manually
     // initialize PyO3.
     pyo3::prepare_freethreaded_python();
 
+    // The packages of a virtualenv are not found through PYTHONHOME. The ones of another
+    // Python version might have binary extensions we can't load, so they're left out.
+    if let Some(interpreter) = interpreter.filter(|i| i.matches_embedded()) {
+        if !interpreter.site_packages.is_empty() {
+            Python::with_gil(|py| -> PyResult<()> {
+                let site = py.import("site")?;
+                for dir in &interpreter.site_packages {
+                    site.call1("addsitedir", (dir.to_str().unwrap_or_default(),))?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+
     Ok(())
 }
 
 /// Check if we're able to start a Python interpreter,
 /// and fail early if we can't.
-pub fn py_handshake(stdout: &mut StandardStream) {
+pub fn py_handshake(interpreter: Option<&PythonInterpreter>) -> Result<Handshake> {
+    let mut warnings = Vec::new();
+    if let Some(interpreter) = interpreter.filter(|i| !i.matches_embedded()) {
+        warnings.push(format!(
+            "psc was built against Python {}, but the selected interpreter is Python {}.
+       PYTHONHOME is left unset and its packages are not loaded, consider using `--backend subprocess`.",
+            embedded_version(),
+            interpreter.version
+        ));
+    }
+
     let pb = get_spinner();
 
     // A quick check whether Python is ready.
     pb.set_message("Initializing Python..");
-    let _ = prepare_python();
+    let _ = prepare_python(interpreter);
     pb.set_message("Python initialized, importing modules..");
-    if exec_py("True", stdout, false).is_err() {
-        panic!("Python interpreter crashed.. Do you have pysprint installed?")
+    if exec_py("True", false).is_err() {
+        pb.finish_and_clear();
+        return Err(Error::Python(String::from(
+            "Python interpreter crashed.. Do you have pysprint installed?\nRun `psc doctor` for details.",
+        )));
     }
-
+    pb.set_message("Checking pysprint version..");
+    let version = eval_py_variable("import pysprint\nversion = pysprint.__version__", "version");
     pb.finish_and_clear();
+
+    let mut handshake = check_pysprint_version(version.ok())?;
+    warnings.append(&mut handshake.warnings);
+    handshake.warnings = warnings;
+    Ok(handshake)
 }
 
-pub fn exec_py(
-    content: &str,
-    stdout: &mut StandardStream,
-    to_file: bool,
-) -> PyResult<(bool, String)> {
+/// Select the templates matching the installed pysprint version, and fail early
+/// if there's none.
+///
+/// Setting `PYSPRINT_SKIP_VERSION_CHECK=1` turns this off, and the newest templates are used.
+pub fn check_pysprint_version(version: Option<String>) -> Result<Handshake> {
+    if matches!(std::env::var("PYSPRINT_SKIP_VERSION_CHECK"), Ok(flag) if flag == "1") {
+        return Ok(Handshake::default());
+    }
+    if let Some(version) = version {
+        Ok(Handshake {
+            warnings: vec![],
+            templates: select_templates_for(&version)?,
+        })
+    } else {
+        Ok(Handshake {
+            warnings: vec![String::from(
+                "Failed to determine the pysprint version, assuming it's supported.",
+            )],
+            templates: Templates::default(),
+        })
+    }
+}
+
+/// Redirects `sys.stdout` and `sys.stderr` into `log`, and optionally
+/// passes everything through to the original streams as well.
+const CAPTURE: &str = r"import io, sys
+class _Tee(io.TextIOBase):
+    def __init__(self, log, stream):
+        self.log, self.stream = log, stream
+    def write(self, s):
+        self.log.write(s)
+        if self.stream is not None:
+            self.stream.write(s)
+        return len(s)
+    def flush(self):
+        if self.stream is not None:
+            self.stream.flush()
+log = io.StringIO()
+saved = sys.stdout, sys.stderr
+sys.stdout = _Tee(log, sys.stdout if show else None)
+sys.stderr = _Tee(log, sys.stderr if show else None)
+";
+
+/// Describes an exception as JSON, with the traceback starting from the frame of `filename`,
+/// which is where the generated code runs.
+pub(crate) const DESCRIBE: &str = r#"import json, traceback
+def describe(etype, value, tb, filename):
+    while tb is not None and tb.tb_frame.f_code.co_filename != filename:
+        tb = tb.tb_next
+    frames = [f for f in traceback.extract_tb(tb) if f.filename == filename]
+    return json.dumps({
+        "exception_type": etype.__name__,
+        "message": str(value),
+        "traceback": "".join(traceback.format_exception(etype, value, tb)),
+        "line": frames[-1].lineno if frames else None,
+    })
+"#;
+
+const RELEASE: &str = "sys.stdout, sys.stderr = saved\noutput = log.getvalue()";
+
+/// Run `content`, and return whether it failed, the error and everything
+/// it printed. The output is only shown as it's produced if `show_output` is set.
+pub fn exec_py(content: &str, show_output: bool) -> PyResult<Execution> {
     // whether this run resulted in an error
     // we count the fails in audit using this variable
     let mut is_err = false;
@@ -95,27 +188,76 @@ pub fn exec_py(
     ]
     .into_py_dict(py);
 
-    let result = py.run(content, None, Some(locals));
+    // remember this thread, so an interrupt can be forwarded to it
+    let ident: c_long = py.import("threading")?.call0("get_ident")?.extract()?;
+    register_python_thread(ident);
 
-    // print Python errors only, stay quiet when Ok(())
+    // collect what the code prints instead of letting it mess up the terminal
+    let capture = [("show", show_output)].into_py_dict(py);
+    py.run(CAPTURE, None, Some(capture))?;
+
+    // surface a pending KeyboardInterrupt even if the code itself succeeded
+    let result = py
+        .run(content, None, Some(locals))
+        .and_then(|()| py.check_signals());
+
+    // The interrupt can also land while the streams are restored, then they're restored
+    // again, otherwise they would keep writing into the log.
+    py.run(RELEASE, None, Some(capture))
+        .or_else(|_| py.run(RELEASE, None, Some(capture)))?;
+    let output = capture
+        .get_item("output")
+        .map(PyAny::extract)
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut exception = None;
     if let Err(ref err) = result {
         is_err = true;
         traceback = err.to_string();
-        if !to_file {
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
-            let py_error = format!("[ERRO] Python error:\n{:?}", err);
-            if let Err(e) = writeln!(stdout, "{}", py_error) {
-                println!("Error writing to stdout: {}", e);
-            }
-            py.check_signals()?;
-            let _ = WriteColor::reset(stdout);
-        }
+        exception = describe(py, err).ok();
     }
-    Ok((is_err, traceback))
+    Ok(Execution {
+        is_err,
+        traceback,
+        output,
+        exception,
+        ..Execution::default()
+    })
 }
 
-pub fn write_err(path: &str, content: &str) -> std::io::Result<()> {
-    let cfg_path = PathBuf::from(path).join("errors.log");
-    std::fs::write(cfg_path, content.as_bytes())?;
-    Ok(())
+/// Collect the details of `err`, raised by code run with `py.run`.
+fn describe(py: Python<'_>, err: &PyErr) -> PyResult<ExceptionInfo> {
+    let namespace = PyDict::new(py);
+    py.run(DESCRIBE, Some(namespace), None)?;
+    let describe = namespace
+        .get_item("describe")
+        .ok_or_else(|| PyNameError::new_err("name 'describe' is not defined"))?;
+    let description: String = describe
+        .call1((
+            err.ptype(py),
+            err.pvalue(py),
+            err.ptraceback(py),
+            "<string>",
+        ))?
+        .extract()?;
+    serde_json::from_str(&description).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Run `content`, and return the value of the string variable `name` it defines.
+pub fn eval_py_variable(content: &str, name: &str) -> PyResult<String> {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let locals = PyDict::new(py);
+    py.run(content, None, Some(locals))?;
+    locals.get_item(name).map_or_else(
+        || {
+            Err(PyNameError::new_err(format!(
+                "name '{}' is not defined",
+                name
+            )))
+        },
+        PyAny::extract,
+    )
 }
@@ -0,0 +1,202 @@
+use crate::backend::Execution;
+use crate::error::{Error, Result};
+use crate::io::{last_lines, OUTPUT_TAIL_LINES};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The message the template exits with when a file is skipped due to its comment.
+const SKIP_MESSAGE: &str = "file skipped due to user comment";
+
+/// The calls in the templates that load the data files.
+const PARSE_CALLS: [&str; 2] = [".parse_raw(", "ps.SPPMethod("];
+
+pub const REPORT_FORMATS: [&str; 2] = ["json", "yaml"];
+
+/// Why the evaluation of a file didn't produce a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Skipped on purpose, because its comment says so.
+    SkippedByComment,
+    /// The file couldn't be loaded.
+    ParseError,
+    /// Anything that went wrong after loading the file.
+    FitFailure,
+    /// The evaluation exceeded its time limit.
+    Timeout,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SkippedByComment => write!(f, "skipped by comment"),
+            Self::ParseError => write!(f, "parse error"),
+            Self::FitFailure => write!(f, "fit failure"),
+            Self::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+impl ErrorCategory {
+    /// Classify a failed evaluation of `code`.
+    pub fn classify(execution: &Execution, code: &str) -> Self {
+        if execution.timed_out {
+            return Self::Timeout;
+        }
+        let Some(exception) = &execution.exception else {
+            return Self::FitFailure;
+        };
+        if exception.exception_type == "SystemExit" && exception.message.starts_with(SKIP_MESSAGE) {
+            return Self::SkippedByComment;
+        }
+        let failed_line = exception
+            .line
+            .and_then(|line| code.lines().nth(line.checked_sub(1)?));
+        if failed_line.is_some_and(|line| PARSE_CALLS.iter().any(|call| line.contains(call))) {
+            Self::ParseError
+        } else {
+            Self::FitFailure
+        }
+    }
+}
+
+/// A single failed evaluation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorEntry {
+    pub file: String,
+    pub category: ErrorCategory,
+    pub exception_type: Option<String>,
+    pub message: String,
+    pub traceback: String,
+    /// The number and the content of the line in the generated code that failed.
+    pub line: Option<usize>,
+    pub code: Option<String>,
+    /// The last lines of the output, and where to find the rest.
+    pub output: String,
+    pub log: Option<PathBuf>,
+    /// The combination of the swept values the file was evaluated with, in a sweep.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sweep: Option<String>,
+}
+
+impl ErrorEntry {
+    pub fn new(file: &str, execution: &Execution, code: &str, log: Option<PathBuf>) -> Self {
+        let exception = execution.exception.clone().unwrap_or_default();
+        Self {
+            file: file.to_owned(),
+            category: ErrorCategory::classify(execution, code),
+            exception_type: Some(exception.exception_type).filter(|t| !t.is_empty()),
+            message: if exception.message.is_empty() {
+                execution.traceback.clone()
+            } else {
+                exception.message
+            },
+            traceback: if exception.traceback.is_empty() {
+                execution.traceback.clone()
+            } else {
+                exception.traceback
+            },
+            line: exception.line,
+            code: exception
+                .line
+                .and_then(|line| code.lines().nth(line.checked_sub(1)?))
+                .map(|line| line.trim().to_owned()),
+            output: last_lines(&execution.output, OUTPUT_TAIL_LINES),
+            log,
+            sweep: None,
+        }
+    }
+}
+
+/// Every failed evaluation of a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    pub errors: Vec<ErrorEntry>,
+}
+
+impl ErrorReport {
+    pub fn push(&mut self, entry: ErrorEntry) {
+        self.errors.push(entry);
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The number of failures in each category.
+    pub fn counts(&self) -> BTreeMap<ErrorCategory, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.errors {
+            *counts.entry(entry.category).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Write the report into `root` as `errors.json` or `errors.yaml`, depending on `format`.
+    pub fn write(&self, root: &str, format: &str) -> Result<PathBuf> {
+        let path = Path::new(root).join(format!("errors.{}", format));
+        let content = match format {
+            "yaml" => serde_yaml::to_string(self)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            "json" => serde_json::to_string_pretty(self)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            other => {
+                return Err(Error::Config(format!(
+                    "expected valid report format, found {}",
+                    other
+                )))
+            }
+        };
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+#[test]
+fn failures_are_classified() {
+    use crate::backend::ExceptionInfo;
+
+    let code = "ifg = ps.FFTMethod.parse_raw(\n    \"a.trt\",\n)\nifg.chdomain()\n";
+    let failed_at = |exception_type: &str, message: &str, line| Execution {
+        is_err: true,
+        exception: Some(ExceptionInfo {
+            exception_type: exception_type.into(),
+            message: message.into(),
+            line: Some(line),
+            ..ExceptionInfo::default()
+        }),
+        ..Execution::default()
+    };
+
+    let skipped = failed_at(
+        "SystemExit",
+        "file skipped due to user comment contains `ref`",
+        8,
+    );
+    assert_eq!(
+        ErrorCategory::classify(&skipped, code),
+        ErrorCategory::SkippedByComment
+    );
+    assert_eq!(
+        ErrorCategory::classify(&failed_at("ValueError", "bad", 1), code),
+        ErrorCategory::ParseError
+    );
+    assert_eq!(
+        ErrorCategory::classify(&failed_at("ValueError", "bad", 4), code),
+        ErrorCategory::FitFailure
+    );
+    let timed_out = Execution {
+        timed_out: true,
+        ..failed_at("TimeoutError", "", 4)
+    };
+    assert_eq!(
+        ErrorCategory::classify(&timed_out, code),
+        ErrorCategory::Timeout
+    );
+
+    let entry = ErrorEntry::new("a.trt", &failed_at("ValueError", "bad", 4), code, None);
+    assert_eq!(entry.code.as_deref(), Some("ifg.chdomain()"));
+}
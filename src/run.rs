@@ -0,0 +1,131 @@
+use crate::backend::Backend;
+use crate::deserialize::Config;
+use crate::error::{Error, Result};
+use crate::evaluator::{Evaluator, Event};
+use crate::io::create_results_file;
+use crate::statistics::{CoeffitientType, DispersionCoeffitient, Evaluated};
+use crate::utils::RunSummary;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::Builder;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+const COEFFICIENT_TYPES: [CoeffitientType; 6] = [
+    CoeffitientType::GD,
+    CoeffitientType::GDD,
+    CoeffitientType::TOD,
+    CoeffitientType::FOD,
+    CoeffitientType::QOD,
+    CoeffitientType::SOD,
+];
+
+/// Evaluate a single interferogram, with its sample and reference arms if they're given,
+/// and print the coefficients. The result is only kept in `result_file` if there's one.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    stdout: &mut StandardStream,
+    file: &Path,
+    sam_: Option<PathBuf>,
+    ref_: Option<PathBuf>,
+    config: Config,
+    result_file: Option<&str>,
+    verbosity: u8,
+    persist: bool,
+    backend: Box<dyn Backend>,
+) -> Result<RunSummary> {
+    let workdir = directory_of(file);
+    for arm in sam_.iter().chain(&ref_) {
+        if directory_of(arm) != workdir {
+            return Err(Error::Config(format!(
+                "the arms must be in the same directory as {:?}, found {:?}",
+                file, arm
+            )));
+        }
+    }
+
+    // the template always writes the result, so it goes into a throwaway file if it's not asked for
+    let scratch = if result_file.is_some() {
+        None
+    } else {
+        let scratch = Builder::new()
+            .prefix(".psc_run")
+            .suffix(".json")
+            .tempfile_in(workdir)?;
+        create_results_file(&scratch.path().to_string_lossy())?;
+        Some(scratch)
+    };
+    let result_file = scratch.as_ref().map_or_else(
+        || result_file.unwrap_or_default().to_owned(),
+        |scratch| {
+            scratch
+                .path()
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or_default()
+                .to_owned()
+        },
+    );
+
+    let evaluation = Evaluator::new(config)
+        .workdir(workdir)
+        .result_file(&result_file)
+        .verbosity(verbosity)
+        .persist(persist)
+        .backend(backend)
+        .on_event(|event| {
+            if let Event::Warning(warning) = event {
+                let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+                let _ = writeln!(stdout, "[WARN] {}", warning);
+                let _ = WriteColor::reset(stdout);
+            }
+        })
+        .run_file(file, sam_, ref_)?;
+
+    for result in &evaluation.results {
+        if let Some(error) = &result.error {
+            let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
+            let _ = writeln!(
+                stdout,
+                "[ERRO] {} failed ({}):\n{}",
+                result.file,
+                error.category,
+                error.traceback.trim_end()
+            );
+            let _ = WriteColor::reset(stdout);
+        } else if let Some(coefficients) = result.coefficients() {
+            let _ = writeln!(stdout, "[INFO] {}:", result.file);
+            for (coeff_type, value) in COEFFICIENT_TYPES.iter().zip(coefficients) {
+                let coeff =
+                    DispersionCoeffitient::with_values_and_type(&[value], coeff_type.clone());
+                if coeff.is_omitted() {
+                    let _ = writeln!(stdout, "       {}: omitted..", coeff_type);
+                } else {
+                    let _ = writeln!(
+                        stdout,
+                        "       {} = {:.5} {}",
+                        coeff_type,
+                        value,
+                        coeff.unit()
+                    );
+                }
+            }
+        }
+    }
+    if scratch.is_none() {
+        let _ = writeln!(
+            stdout,
+            "[INFO] Result written to {:?}.",
+            Path::new(workdir).join(&result_file)
+        );
+    }
+    Ok(evaluation.summary)
+}
+
+/// The directory of `path`, `.` if it has none.
+fn directory_of(path: &Path) -> &str {
+    path.parent()
+        .and_then(Path::to_str)
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or(".")
+}
@@ -1,6 +1,7 @@
+use crate::error::{Error, Result};
+use crate::sweep::SweepResult;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
@@ -171,11 +172,14 @@ impl DispersionCoeffitient {
     }
 }
 
-fn read_results_from_file<P: AsRef<Path>>(path: P) -> Result<JsonMap, Box<dyn Error>> {
-    let file = File::open(path)?;
+pub(crate) fn read_results_from_file<P: AsRef<Path>>(path: P) -> Result<JsonMap> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .map_err(|e| Error::ResultFile(format!("failed to open {:?}: {}", path, e)))?;
     let reader = BufReader::new(file);
 
-    let map: JsonMap = serde_json::from_reader(reader)?;
+    let map: JsonMap = serde_json::from_reader(reader)
+        .map_err(|e| Error::ResultFile(format!("failed to parse {:?}: {}", path, e)))?;
 
     Ok(map)
 }
@@ -184,46 +188,151 @@ fn read_results_from_file<P: AsRef<Path>>(path: P) -> Result<JsonMap, Box<dyn Er
 macro_rules! extract_coeff {
     ($name:tt, $v:ident) => {{
         let coeff: f64 = match &$v[$name] {
-            Value::String(val) => val.parse::<f64>().unwrap(),
-            Value::Number(val) => val.as_f64().unwrap(),
+            Value::String(val) => val.parse::<f64>().unwrap_or(0.0),
+            Value::Number(val) => val.as_f64().unwrap_or(0.0),
             _ => 0.0,
         };
         coeff
     }};
 }
 
-pub fn summarize<P: AsRef<Path>>(path: P) {
-    let map = read_results_from_file(path).unwrap();
-
-    let mut gds = DispersionCoeffitient::empty_with_type(CoeffitientType::GD);
-    let mut gdds = DispersionCoeffitient::empty_with_type(CoeffitientType::GDD);
-    let mut tods = DispersionCoeffitient::empty_with_type(CoeffitientType::TOD);
-    let mut fods = DispersionCoeffitient::empty_with_type(CoeffitientType::FOD);
-    let mut qods = DispersionCoeffitient::empty_with_type(CoeffitientType::QOD);
-    let mut sods = DispersionCoeffitient::empty_with_type(CoeffitientType::SOD);
-    let mut method: &str = "";
-
-    for v in map.values() {
-        let curr_gd = extract_coeff!("GD", v);
-        gds.push(curr_gd);
-        let curr_gdd = extract_coeff!("GDD", v);
-        gdds.push(curr_gdd);
-        let curr_tod = extract_coeff!("TOD", v);
-        tods.push(curr_tod);
-        let curr_fod = extract_coeff!("FOD", v);
-        fods.push(curr_fod);
-        let curr_qod = extract_coeff!("QOD", v);
-        qods.push(curr_qod);
-        let curr_sod = extract_coeff!("SOD", v);
-        sods.push(curr_sod);
-        method = v["method"].as_str().unwrap_or("unknown");
-    }
-    println!("{} entries found.", gds.len());
+/// The GD, GDD, TOD, FOD, QOD and SOD values of a single result entry.
+pub fn entry_coefficients(v: &Value) -> [f64; 6] {
+    [
+        extract_coeff!("GD", v),
+        extract_coeff!("GDD", v),
+        extract_coeff!("TOD", v),
+        extract_coeff!("FOD", v),
+        extract_coeff!("QOD", v),
+        extract_coeff!("SOD", v),
+    ]
+}
+
+/// Collect every coefficient type from the given result entries.
+pub fn collect_coefficients<'a, I>(entries: I) -> Vec<DispersionCoeffitient>
+where
+    I: IntoIterator<Item = &'a Value>,
+{
+    let mut coeffs = vec![
+        DispersionCoeffitient::empty_with_type(CoeffitientType::GD),
+        DispersionCoeffitient::empty_with_type(CoeffitientType::GDD),
+        DispersionCoeffitient::empty_with_type(CoeffitientType::TOD),
+        DispersionCoeffitient::empty_with_type(CoeffitientType::FOD),
+        DispersionCoeffitient::empty_with_type(CoeffitientType::QOD),
+        DispersionCoeffitient::empty_with_type(CoeffitientType::SOD),
+    ];
+
+    // entries recording a failed evaluation have no coefficients
+    for v in entries.into_iter().filter(|v| v.get("error").is_none()) {
+        for (coeff, value) in coeffs.iter_mut().zip(entry_coefficients(v)) {
+            coeff.push(value);
+        }
+    }
+    coeffs
+}
+
+/// Print the statistics of the result file. Returns `false` if every evaluation in it failed.
+pub fn summarize<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let map = read_results_from_file(path)?;
+    if let Some(sweep) = map.get("sweep") {
+        let sweep: Vec<SweepResult> = serde_json::from_value(sweep.clone())
+            .map_err(|e| Error::ResultFile(format!("failed to parse the sweep results: {}", e)))?;
+        return Ok(summarize_sweep(&sweep));
+    }
+
+    let coeffs = collect_coefficients(map.values());
+    let method = map
+        .values()
+        .filter(|v| v.get("error").is_none())
+        .last()
+        .map_or("", |v| v["method"].as_str().unwrap_or("unknown"));
+
+    println!("{} entries found.", coeffs[0].len());
+    let failed = map.len() - coeffs[0].len();
+    if failed > 0 {
+        println!("{} failed evaluations skipped.", failed);
+    }
     println!("method: {}", method);
-    println!("{}", gds);
-    println!("{}", gdds);
-    println!("{}", tods);
-    println!("{}", fods);
-    println!("{}", qods);
-    println!("{}", sods);
+    for coeff in &coeffs {
+        println!("{}", coeff);
+    }
+    Ok(failed < map.len() || map.is_empty())
+}
+
+/// Print a table of the coefficients' mean and standard deviation, a row for every
+/// combination of the swept values. Returns `false` if every evaluation failed.
+fn summarize_sweep(sweep: &[SweepResult]) -> bool {
+    let rows = sweep
+        .iter()
+        .map(|result| (result, collect_coefficients(result.results.values())))
+        .collect::<Vec<_>>();
+    let keys = sweep
+        .first()
+        .map(|result| result.parameters.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    // a combination might not set every key, if the sweep of the result file was edited
+    let parameter = |result: &SweepResult, key: &str| {
+        result
+            .parameters
+            .get(key)
+            .map_or_else(|| String::from("-"), ToString::to_string)
+    };
+    // the GD has no meaningful mean, and omitted coefficients are left out entirely
+    let shown = (1..6)
+        .filter(|&i| rows.iter().any(|(_, coeffs)| !coeffs[i].is_omitted()))
+        .collect::<Vec<_>>();
+
+    let widths = keys
+        .iter()
+        .map(|key| {
+            rows.iter()
+                .map(|(result, _)| parameter(result, key).len())
+                .chain(std::iter::once(key.len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = keys
+        .iter()
+        .zip(&widths)
+        .map(|(key, width)| format!("{:<width$}", key, width = width))
+        .collect::<Vec<_>>();
+    header.push(String::from("entries"));
+    for &i in &shown {
+        let coeff_type = &rows[0].1[i];
+        header.push(format!("{:>12}", format!("{} mean", coeff_type.coeff_type)));
+        header.push(format!("{:>12}", format!("{} std", coeff_type.coeff_type)));
+    }
+    println!("{} combinations found.", rows.len());
+    println!("{}", header.join(" | "));
+
+    let mut succeeded = 0;
+    for (result, coeffs) in &rows {
+        succeeded += coeffs[0].len();
+        let mut row = keys
+            .iter()
+            .zip(&widths)
+            .map(|(key, width)| format!("{:<width$}", parameter(result, key), width = width))
+            .collect::<Vec<_>>();
+        row.push(format!(
+            "{:>7}",
+            format!("{}/{}", coeffs[0].len(), result.results.len())
+        ));
+        for &i in &shown {
+            if let (Some(mean), Some(std)) = (coeffs[i].mean(), coeffs[i].std_deviation()) {
+                row.push(format!("{:>12.5}", mean));
+                row.push(format!("{:>12.5}", std));
+            } else {
+                row.push(format!("{:>12}", "-"));
+                row.push(format!("{:>12}", "-"));
+            }
+        }
+        println!("{}", row.join(" | "));
+    }
+    let total = rows
+        .iter()
+        .map(|(result, _)| result.results.len())
+        .sum::<usize>();
+    succeeded > 0 || total == 0
 }
@@ -0,0 +1,208 @@
+use crate::audit::{evaluate, write_error_report};
+use crate::backend::Backend;
+use crate::deserialize::{Config, SweepValues};
+use crate::error::{Error, Result};
+use crate::io::create_results_file;
+use crate::parser::{parse_with_overrides, Override};
+use crate::report::ErrorReport;
+use crate::statistics::read_results_from_file;
+use crate::utils::RunSummary;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::Builder;
+use termcolor::StandardStream;
+
+/// The results of the evaluation with one combination of the swept values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepResult {
+    /// The value of every swept key.
+    pub parameters: BTreeMap<String, Value>,
+    pub results: HashMap<String, Value>,
+}
+
+impl SweepResult {
+    /// The parameters as `key=value` pairs.
+    pub fn label(&self) -> String {
+        self.parameters
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Every combination of the swept values, as the overrides setting them.
+pub fn combinations(sweep: &BTreeMap<String, SweepValues>) -> Result<Vec<Vec<Override>>> {
+    let mut combinations = vec![vec![]];
+    for (key, values) in sweep {
+        let values = values.values();
+        if values.is_empty() {
+            return Err(Error::Config(format!("the sweep of {} has no values", key)));
+        }
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination: Vec<Override>| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push(Override {
+                        key: key.clone(),
+                        value: value.clone(),
+                    });
+                    combination
+                })
+            })
+            .collect();
+    }
+    Ok(combinations)
+}
+
+/// Run `audit` in `filepath` with every combination of the `sweep` values.
+///
+/// The values are applied to `config_file` after the `profile` and `overrides`, and the results are collected
+/// into `result_file` by their combination. The failed evaluations of every combination are
+/// written into a single report. A backend is made for every combination with `backend`,
+/// as the interpreter might be swept too.
+#[allow(clippy::too_many_arguments)]
+pub fn sweep<F>(
+    stdout: &mut StandardStream,
+    filepath: &str,
+    config_file: &str,
+    profile: Option<&str>,
+    overrides: &[Override],
+    sweep: &BTreeMap<String, SweepValues>,
+    files: Option<&[PathBuf]>,
+    result_file: &str,
+    verbosity: u8,
+    persist: bool,
+    error_format: &str,
+    mut backend: F,
+) -> Result<RunSummary>
+where
+    F: FnMut(&mut StandardStream, &Config) -> Result<Box<dyn Backend>>,
+{
+    // every combination is checked before the first one is evaluated
+    let runs = combinations(sweep)?
+        .into_iter()
+        .map(|combination| {
+            let all = overrides
+                .iter()
+                .chain(&combination)
+                .cloned()
+                .collect::<Vec<_>>();
+            let (config, effective) = parse_with_overrides(config_file, profile, &all)?;
+            let parameters = effective
+                .into_iter()
+                .skip(overrides.len())
+                .map(|(key, value)| (key, serde_json::to_value(value).unwrap_or_default()))
+                .collect::<BTreeMap<_, _>>();
+            Ok((config, parameters))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // the results of a single combination are collected in a throwaway file
+    let scratch = Builder::new()
+        .prefix(".psc_sweep")
+        .suffix(".json")
+        .tempfile_in(filepath)?;
+    let scratch_file = scratch
+        .path()
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_owned();
+
+    let total = runs.len();
+    let mut results = vec![];
+    let mut summary = RunSummary::default();
+    let mut errors = ErrorReport::default();
+    for (i, (config, parameters)) in runs.into_iter().enumerate() {
+        let mut result = SweepResult {
+            parameters,
+            results: HashMap::new(),
+        };
+        let _ = writeln!(
+            stdout,
+            "[INFO] Sweep {} of {}: {}",
+            i + 1,
+            total,
+            result.label()
+        );
+        create_results_file(&scratch.path().to_string_lossy())?;
+        let backend = backend(stdout, &config)?;
+        let evaluation = evaluate(
+            stdout,
+            filepath,
+            config,
+            files.map(<[_]>::to_vec),
+            &scratch_file,
+            verbosity,
+            persist,
+            backend,
+        )?;
+        let run = &evaluation.summary;
+        summary.merge(run);
+        let label = result.label();
+        errors.errors.extend(
+            evaluation
+                .error_report()
+                .errors
+                .into_iter()
+                .map(|mut error| {
+                    error.sweep = Some(label.clone());
+                    error
+                }),
+        );
+
+        result.results = read_results_from_file(scratch.path())?;
+        results.push(result);
+        write_sweep_results(&Path::new(filepath).join(result_file), &results)?;
+        if run.interrupted {
+            break;
+        }
+    }
+    let _ = writeln!(
+        stdout,
+        "[INFO] Results of {} of {} combinations written to {:?}.",
+        results.len(),
+        total,
+        Path::new(filepath).join(result_file)
+    );
+    write_error_report(&errors, filepath, error_format);
+    Ok(summary)
+}
+
+fn write_sweep_results(path: &Path, results: &[SweepResult]) -> Result<()> {
+    let content = serde_json::to_string_pretty(&serde_json::json!({ "sweep": results }))
+        .map_err(|e| Error::ResultFile(format!("failed to serialize the results: {}", e)))?;
+    std::fs::write(path, content)
+        .map_err(|e| Error::ResultFile(format!("failed to write {:?}: {}", path, e)))
+}
+
+#[test]
+fn every_combination_is_swept() {
+    let sweep: BTreeMap<String, SweepValues> = serde_yaml::from_str(
+        "
+evaluate.reference_frequency: [2.3, 2.4]
+method_details.fwhm: { start: 0.01, stop: 0.03, step: 0.01 }
+",
+    )
+    .unwrap();
+    let swept = combinations(&sweep).unwrap();
+    assert_eq!(swept.len(), 6);
+    let values = |combination: &[Override]| {
+        combination
+            .iter()
+            .map(|o| o.value.as_f64().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(&swept[0]), [2.3, 0.01]);
+    assert_eq!(values(&swept[2]), [2.3, 0.03]);
+    assert_eq!(values(&swept[5]), [2.4, 0.03]);
+
+    let empty: BTreeMap<String, SweepValues> = serde_yaml::from_str("evaluate.order: []").unwrap();
+    assert!(combinations(&empty).is_err());
+}
@@ -1,9 +1,13 @@
 use crate::codegen::maybe_write_default_yaml;
 use crate::deserialize::LoadOptions;
+use crate::error::{Error, Result};
 use crate::io::create_results_file;
+use crate::parser::{discover_config, CONFIG_NAMES};
+use crate::report::ErrorCategory;
 use clap::ArgMatches;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 use wildmatch::WildMatch;
@@ -11,88 +15,287 @@ use wildmatch::WildMatch;
 #[derive(Debug, Clone)]
 pub struct StartupOptions {
     pub filepath: String,
+    /// The path of the config file, it's not necessarily in `filepath`.
     pub config_file: String,
     pub result_file: String,
     pub verbosity: u8,
     pub persist: bool,
+    pub dashboard: Option<usize>,
+    pub backend: String,
+    pub python: Option<String>,
+    pub show_python_output: bool,
+    pub error_format: String,
+    pub max_failures: Option<usize>,
+}
+
+/// How the questions asked on the terminal get answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    /// Ask the user.
+    Interactive,
+    /// Answer yes to everything, set by `--yes`.
+    AssumeYes,
+    /// Don't ask, take the default answer (no) instead. Set by `--no-input`,
+    /// or when stdin is not a terminal.
+    NoInput,
+}
+
+impl InputMode {
+    pub fn from_matches(matches: &ArgMatches<'_>) -> Self {
+        if matches.is_present("yes") {
+            Self::AssumeYes
+        } else if matches.is_present("no-input") || !std::io::stdin().is_terminal() {
+            Self::NoInput
+        } else {
+            Self::Interactive
+        }
+    }
+
+    /// Answer a yes or no question, asking the user only in interactive mode.
+    pub fn confirm(self) -> std::io::Result<bool> {
+        match self {
+            Self::Interactive => {
+                let mut input_text = String::new();
+                std::io::stdin().read_line(&mut input_text)?;
+                Ok(matches!(input_text.to_lowercase().trim(), "yes" | "y"))
+            }
+            Self::AssumeYes => Ok(true),
+            Self::NoInput => Ok(false),
+        }
+    }
+}
+
+/// Everything went fine.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Any failure that doesn't have a more specific code.
+pub const EXIT_FAILURE: i32 = 1;
+/// The config file or the command line options are invalid.
+pub const EXIT_CONFIG_ERROR: i32 = 2;
+/// Python or pysprint couldn't be initialized.
+pub const EXIT_PYTHON_ERROR: i32 = 3;
+/// More files failed than allowed by `--fail-on-error` or `--max-failures`.
+pub const EXIT_PARTIAL_FAILURE: i32 = 4;
+/// Every evaluated file failed.
+pub const EXIT_TOTAL_FAILURE: i32 = 5;
+/// The run was interrupted with Ctrl-C.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// What happened during an `audit` or `watch` run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    /// The number of files selected for evaluation, 0 if it's not known upfront.
+    pub total: usize,
+    pub processed: usize,
+    pub failed: usize,
+    /// The number of failures by their category, if they were classified.
+    pub failures: BTreeMap<ErrorCategory, usize>,
+    pub interrupted: bool,
+}
+
+impl RunSummary {
+    pub fn report(&self, stdout: &mut StandardStream) {
+        if self.interrupted {
+            let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+            let _ = writeln!(stdout, "[WARN] Interrupted by user.");
+            let _ = WriteColor::reset(stdout);
+        }
+        let processed = if self.total > 0 {
+            format!("{} of {}", self.processed, self.total)
+        } else {
+            self.processed.to_string()
+        };
+        if let Err(e) = writeln!(
+            stdout,
+            "[INFO] {} files processed, {} skipped or errored out.",
+            processed, self.failed
+        ) {
+            println!("Error writing to stdout: {}", e);
+        }
+        if !self.failures.is_empty() {
+            let failures = [
+                ErrorCategory::SkippedByComment,
+                ErrorCategory::ParseError,
+                ErrorCategory::FitFailure,
+                ErrorCategory::Timeout,
+            ]
+            .iter()
+            .map(|category| {
+                format!(
+                    "{}: {}",
+                    category,
+                    self.failures.get(category).copied().unwrap_or(0)
+                )
+            })
+            .collect::<Vec<_>>();
+            let _ = writeln!(stdout, "       {}", failures.join(", "));
+        }
+    }
+
+    /// Add the counts of `other` to this run, e.g. when it's made up of several audits.
+    pub fn merge(&mut self, other: &Self) {
+        self.total += other.total;
+        self.processed += other.processed;
+        self.failed += other.failed;
+        for (category, count) in &other.failures {
+            *self.failures.entry(*category).or_default() += count;
+        }
+        self.interrupted |= other.interrupted;
+    }
+
+    /// The process exit code belonging to this run. Files skipped on purpose don't count
+    /// as failures, and only more than `max_failures` of them fail the run, if it's given.
+    pub fn exit_code(&self, max_failures: Option<usize>) -> i32 {
+        if self.interrupted {
+            return EXIT_INTERRUPTED;
+        }
+        let skipped = self
+            .failures
+            .get(&ErrorCategory::SkippedByComment)
+            .copied()
+            .unwrap_or(0);
+        let errors = self.failed.saturating_sub(skipped);
+        if errors > 0 && errors + skipped >= self.processed {
+            EXIT_TOTAL_FAILURE
+        } else if max_failures.is_some_and(|max| errors > max) {
+            EXIT_PARTIAL_FAILURE
+        } else {
+            EXIT_SUCCESS
+        }
+    }
 }
 
 pub fn get_startup_options(
     matches: &ArgMatches<'_>,
     stdout: &mut StandardStream,
-) -> Option<StartupOptions> {
+) -> Result<StartupOptions> {
     let verbosity: u8 = match matches.occurrences_of("verbosity") {
         0 => 0,
         _ => 1,
     };
     let persist = matches.is_present("persist");
+    let dashboard = if matches.is_present("dashboard") {
+        Some(
+            matches
+                .value_of("history")
+                .and_then(|h| h.parse::<usize>().ok())
+                .unwrap_or(10),
+        )
+    } else {
+        None
+    };
 
-    if let Some(filepath) = matches.value_of("path") {
-        let config_file = matches.value_of("config").unwrap_or("eval.yaml");
-        let config_filepath = Path::new(&filepath).join(config_file);
-        if !config_filepath.exists() {
-            let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+    let input_mode = InputMode::from_matches(matches);
+    let Some(filepath) = matches.value_of("path") else {
+        return Err(Error::Config(String::from("no path given")));
+    };
+    let config_file = if let Some(config_file) = matches.value_of("config") {
+        // an explicit config is never replaced by the default one
+        let config_file = Path::new(filepath).join(config_file);
+        if !config_file.exists() {
+            return Err(Error::Config(format!(
+                "the config file {:?} doesn't exist",
+                config_file
+            )));
+        }
+        let _ = writeln!(stdout, "[INFO] Using config {:?}.", config_file);
+        config_file
+    } else if let Some(config_file) = discover_config(Path::new(filepath)) {
+        let _ = writeln!(stdout, "[INFO] Using config {:?}.", config_file);
+        config_file
+    } else {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+        let _ = writeln!(
+            stdout,
+            "[WARN] No {} found in {:?}, its parents or the user config directory.",
+            CONFIG_NAMES.join(", "),
+            filepath
+        );
+        let _ = WriteColor::reset(stdout);
+        maybe_write_default_yaml(filepath, input_mode)?;
+        Path::new(filepath).join(CONFIG_NAMES[0])
+    };
+
+    let result_file = matches.value_of("result").unwrap_or("results.json");
+    let result_filepath = Path::new(&filepath).join(result_file);
+    // a dry run leaves the result file alone
+    if !matches.is_present("dry-run") {
+        prepare_result_file(matches, stdout, input_mode, &result_filepath)?;
+    }
+
+    Ok(StartupOptions {
+        filepath: filepath.into(),
+        config_file: config_file.to_string_lossy().into_owned(),
+        result_file: result_file.into(),
+        verbosity,
+        persist,
+        dashboard,
+        backend: matches.value_of("backend").unwrap_or("embedded").into(),
+        python: matches.value_of("python").map(String::from),
+        show_python_output: matches.is_present("show-python-output"),
+        error_format: matches.value_of("error-report").unwrap_or("json").into(),
+        max_failures: if matches.is_present("fail-on-error") {
+            Some(0)
+        } else {
+            matches
+                .value_of("max-failures")
+                .and_then(|n| n.parse::<usize>().ok())
+        },
+    })
+}
+
+/// Create the result file, or override it if it's allowed to.
+fn prepare_result_file(
+    matches: &ArgMatches<'_>,
+    stdout: &mut StandardStream,
+    input_mode: InputMode,
+    result_filepath: &Path,
+) -> Result<()> {
+    let create = |path: &Path| {
+        create_results_file(&path.to_string_lossy())
+            .map_err(|e| Error::ResultFile(format!("failed to create {:?}: {}", path, e)))
+    };
+    if matches.is_present("override") {
+        if result_filepath.exists() {
             let _ = writeln!(
                 stdout,
-                "[WARN] File named {:?} currently doesn't exist.",
-                config_filepath
+                "[INFO] Overriding result file at {:?}.",
+                result_filepath
             );
-            let _ = WriteColor::reset(stdout);
-            maybe_write_default_yaml(filepath);
+        } else if let Err(e) = writeln!(stdout, "[INFO] Created {:?} result file.", result_filepath)
+        {
+            println!("Error writing to stdout: {}", e);
         }
-
-        let result_file = matches.value_of("result").unwrap_or("results.json");
-        let result_filepath = Path::new(&filepath).join(result_file);
-        if matches.is_present("override") {
-            if result_filepath.exists() {
-                let _ = writeln!(
-                    stdout,
-                    "[INFO] Overriding result file at {:?}.",
-                    &result_filepath
-                );
-            } else if let Err(e) = writeln!(
-                stdout,
-                "[INFO] Created {:?} result file.",
-                result_filepath.to_str().unwrap()
-            ) {
-                println!("Error writing to stdout: {}", e);
-            }
-            create_results_file(result_filepath.into_os_string().to_str().unwrap()).unwrap();
-        } else if !result_file_is_present(&result_filepath, stdout).unwrap_or(true) {
-            create_results_file(result_filepath.into_os_string().to_str().unwrap()).unwrap();
-        } else {
+        create(result_filepath)?;
+    } else if !result_file_is_present(result_filepath, stdout).unwrap_or(true) {
+        create(result_filepath)?;
+    } else {
+        if input_mode == InputMode::Interactive {
             let _ = writeln!(
                 stdout,
                 "[INFO] Type 'yes' or 'y' to override it, or anything else to quit.",
             );
-            if maybe_override_results_file() {
-                create_results_file(result_filepath.clone().into_os_string().to_str().unwrap())
-                    .unwrap();
-                let _ = writeln!(
-                    stdout,
-                    "[INFO] Result file overridden at {:?}.",
-                    &result_filepath.to_str().unwrap()
-                );
-            } else {
-                panic!("failed to find a writeable result file.");
-            }
         }
-
-        return Some(StartupOptions {
-            filepath: filepath.into(),
-            config_file: config_file.into(),
-            result_file: result_file.into(),
-            verbosity,
-            persist,
-        });
+        if input_mode.confirm()? {
+            create(result_filepath)?;
+            let _ = writeln!(
+                stdout,
+                "[INFO] Result file overridden at {:?}.",
+                result_filepath
+            );
+        } else {
+            return Err(Error::ResultFile(format!(
+                "{:?} already exists, and it was not overridden (use `--override` or `--yes` to do so)",
+                result_filepath
+            )));
+        }
     }
-    None
+    Ok(())
 }
 
 pub fn result_file_is_present<P: AsRef<Path>>(
     result_filepath: P,
     stdout: &mut StandardStream,
-) -> Result<bool, Box<dyn std::error::Error>> {
+) -> Result<bool> {
     if result_filepath.as_ref().exists() {
         stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
         let warning = format!(
@@ -108,7 +311,7 @@ pub fn result_file_is_present<P: AsRef<Path>>(
         if let Err(e) = writeln!(
             stdout,
             "[INFO] Created {:?} result file.",
-            result_filepath.as_ref().file_name().unwrap()
+            result_filepath.as_ref().file_name().unwrap_or_default()
         ) {
             println!("Error writing to stdout: {}", e);
         }
@@ -150,26 +353,15 @@ pub fn get_spinner() -> ProgressBar {
     pb
 }
 
-pub fn sort_by_arms(
-    files: &[PathBuf],
-    stdout: &mut StandardStream,
-    warn: bool,
-) -> (Vec<PathBuf>, Vec<PathBuf>, Vec<PathBuf>) {
+/// Split `files` into the interferogram, sample and reference arms, in this order.
+/// The hanging files at the end are left out, if the number of files is not divisible by 3.
+pub fn sort_by_arms(files: &[PathBuf]) -> (Vec<PathBuf>, Vec<PathBuf>, Vec<PathBuf>) {
     let mut ifgs = Vec::<PathBuf>::new();
     let mut sams = Vec::<PathBuf>::new();
     let mut refs = Vec::<PathBuf>::new();
 
     // exclude the hanging files, the arms missmatch somewhere
     let n = files.len() - files.len() % 3;
-    if n != files.len() && warn {
-        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
-        let _ = writeln!(
-            stdout,
-            "[WARN] The number of files is not divisible by 3, it's very likely that this command will fail..
-       Maybe you forgot to exclude/include some?"
-        );
-        let _ = WriteColor::reset(stdout);
-    }
     for file in files.iter().take(n).step_by(3) {
         ifgs.push(file.to_path_buf());
     }
@@ -182,12 +374,31 @@ pub fn sort_by_arms(
     (ifgs, sams, refs)
 }
 
-#[must_use]
-pub fn maybe_override_results_file() -> bool {
-    let mut input_text = String::new();
-    std::io::stdin()
-        .read_line(&mut input_text)
-        .expect("failed to read from stdin");
+#[test]
+fn exit_code_reflects_failures() {
+    let summary = |processed, failed, skipped| RunSummary {
+        total: processed,
+        processed,
+        failed,
+        failures: std::iter::once((ErrorCategory::SkippedByComment, skipped)).collect(),
+        interrupted: false,
+    };
+    assert_eq!(summary(4, 0, 0).exit_code(Some(0)), EXIT_SUCCESS);
+    assert_eq!(summary(4, 1, 0).exit_code(None), EXIT_SUCCESS);
+    assert_eq!(summary(4, 1, 0).exit_code(Some(0)), EXIT_PARTIAL_FAILURE);
+    assert_eq!(summary(4, 2, 0).exit_code(Some(2)), EXIT_SUCCESS);
+    assert_eq!(summary(4, 1, 1).exit_code(Some(0)), EXIT_SUCCESS);
+    assert_eq!(summary(4, 4, 1).exit_code(None), EXIT_TOTAL_FAILURE);
+    assert_eq!(summary(4, 4, 4).exit_code(None), EXIT_SUCCESS);
+    let interrupted = RunSummary {
+        interrupted: true,
+        ..summary(4, 4, 0)
+    };
+    assert_eq!(interrupted.exit_code(None), EXIT_INTERRUPTED);
+}
 
-    matches!(input_text.to_lowercase().trim(), "yes" | "y")
+#[test]
+fn non_interactive_modes_answer_without_asking() {
+    assert!(InputMode::AssumeYes.confirm().unwrap());
+    assert!(!InputMode::NoInput.confirm().unwrap());
 }